@@ -0,0 +1,96 @@
+use syn::{
+    parse::{ParseStream, Result as ParseResult},
+    punctuated::Punctuated,
+    visit::Visit,
+    Generics, Ident, LitStr, Path, Token, WherePredicate,
+};
+
+/// The where-clause predicates required by the fields of a derived type.
+#[derive(Debug, Clone, Default)]
+pub struct Bounds {
+    inferred: Vec<WherePredicate>,
+    explicit: Vec<WherePredicate>,
+}
+
+impl Bounds {
+    /// Construct a set of bounds given explicitly with a `bound` option.
+    pub fn explicit(preds: Vec<WherePredicate>) -> Self {
+        Self {
+            inferred: vec![],
+            explicit: preds,
+        }
+    }
+
+    /// Add predicates inferred from the way a field is disposed.  Any of these
+    /// that do not mention a type parameter of the derived type are dropped
+    /// when the bounds are applied.
+    pub fn infer(&mut self, preds: impl IntoIterator<Item = WherePredicate>) {
+        self.inferred.extend(preds);
+    }
+
+    /// Add all bounds from `other` to this set.
+    pub fn append(&mut self, other: Self) {
+        self.inferred.extend(other.inferred);
+        self.explicit.extend(other.explicit);
+    }
+
+    /// Add these bounds to the where clause of `generics`.
+    pub fn apply(self, generics: &mut Generics) {
+        let params: Vec<_> = generics.type_params().map(|p| p.ident.clone()).collect();
+
+        let inferred = self.inferred.into_iter().filter(|pred| {
+            let mut visit = MentionsParam {
+                params: &params,
+                found: false,
+            };
+            visit.visit_where_predicate(pred);
+
+            visit.found
+        });
+
+        let mut preds: Vec<WherePredicate> = vec![];
+
+        for pred in inferred.chain(self.explicit) {
+            if !preds.contains(&pred) {
+                preds.push(pred);
+            }
+        }
+
+        if !preds.is_empty() {
+            generics.make_where_clause().predicates.extend(preds);
+        }
+    }
+}
+
+/// Checks whether any path visited begins with one of a set of type
+/// parameters, e.g. `T` or `T::Assoc`.
+struct MentionsParam<'a> {
+    params: &'a [Ident],
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for MentionsParam<'_> {
+    fn visit_path(&mut self, path: &'ast Path) {
+        if path.leading_colon.is_none()
+            && path
+                .segments
+                .first()
+                .is_some_and(|s| self.params.contains(&s.ident))
+        {
+            self.found = true;
+        }
+
+        syn::visit::visit_path(self, path);
+    }
+}
+
+/// Parse the remainder of a `bound = "..."` option, producing the predicates
+/// contained in the string literal.
+pub fn parse_bound(input: ParseStream) -> ParseResult<Vec<WherePredicate>> {
+    input.parse::<Token![=]>()?;
+    let lit = input.parse::<LitStr>()?;
+
+    let preds = lit.parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+
+    Ok(preds.into_iter().collect())
+}
@@ -1,24 +1,27 @@
 use proc_macro2::TokenStream;
-use quote::ToTokens;
 use syn::{
     ext::IdentExt,
-    parenthesized,
     parse::{Error as ParseError, Parse, ParseStream, Parser, Result as ParseResult},
     spanned::Spanned,
-    token, AttrStyle, Attribute, Ident, Token,
+    AttrStyle, Attribute, Ident, LitInt, Member, Meta, Token, Type, WherePredicate,
 };
 
-use super::WithVal;
+use super::{bound::parse_bound, WithVal};
 
 #[derive(Debug, Clone, Default)]
 pub struct FieldAttr {
-    pub mode: FieldMode,
+    pub mode: Option<FieldMode>,
+    pub concurrent: Option<Ident>,
+    pub order: Option<i32>,
+    pub after: Vec<Member>,
+    pub bound: Option<Vec<WherePredicate>>,
 }
 
 #[derive(Debug, Clone)]
 pub enum FieldMode {
     Dispose { is_iter: bool },
     DisposeWith { is_iter: bool, with: WithVal },
+    DisposeWithContext(Box<Type>),
     Ignore,
 }
 
@@ -26,56 +29,123 @@ impl Default for FieldMode {
     fn default() -> Self { FieldMode::Dispose { is_iter: false } }
 }
 
+impl FieldMode {
+    /// Parse the remainder of a field mode beginning with `ident`, returning
+    /// `None` if `ident` does not name a mode.
+    pub fn parse_rest(ident: &Ident, input: ParseStream) -> ParseResult<Option<Self>> {
+        Ok(Some(match ident {
+            i if i == "ignore" => FieldMode::Ignore,
+            i if i == "with" => {
+                input.parse::<Token![=]>()?;
+
+                FieldMode::DisposeWith {
+                    is_iter: false,
+                    with: input.parse()?,
+                }
+            },
+            i if i == "with_context" => {
+                input.parse::<Token![=]>()?;
+
+                FieldMode::DisposeWithContext(input.parse()?)
+            },
+            i if i == "iter" => FieldMode::Dispose { is_iter: true },
+            i if i == "iter_with" => {
+                input.parse::<Token![=]>()?;
+
+                FieldMode::DisposeWith {
+                    is_iter: true,
+                    with: input.parse()?,
+                }
+            },
+            _ => return Ok(None),
+        }))
+    }
+}
+
 impl Parse for FieldAttr {
     fn parse(input: ParseStream) -> ParseResult<Self> {
-        if input.peek(token::Paren) {
-            let arg;
-            parenthesized!(arg in input);
-
-            if arg.peek(Ident::peek_any) {
-                let ident = arg.call(Ident::parse_any)?;
-
-                let mode = match ident {
-                    i if i == "ignore" => FieldMode::Ignore,
-                    i if i == "with" => {
-                        arg.parse::<Token![=]>()?;
-
-                        FieldMode::DisposeWith {
-                            is_iter: false,
-                            with: arg.parse()?,
-                        }
-                    },
-                    i if i == "iter" => FieldMode::Dispose { is_iter: true },
-                    i if i == "iter_with" => {
-                        arg.parse::<Token![=]>()?;
-
-                        FieldMode::DisposeWith {
-                            is_iter: true,
-                            with: arg.parse()?,
-                        }
-                    },
-                    i => {
-                        return Err(ParseError::new(
-                            i.span(),
-                            "expected `ignore`, `with`, `iter`, or `iter_with`",
-                        ));
-                    },
-                };
+        let mut ret = Self::default();
 
-                Ok(Self { mode })
-            } else {
-                Ok(Self::default())
+        while !input.is_empty() {
+            let ident = input.call(Ident::parse_any)?;
+
+            if ident == "concurrent" {
+                if ret.concurrent.is_some() {
+                    return Err(ParseError::new(ident.span(), "duplicate `concurrent` option"));
+                }
+
+                ret.concurrent = Some(ident);
+                parse_comma(input)?;
+                continue;
+            }
+
+            if ident == "order" {
+                if ret.order.is_some() {
+                    return Err(ParseError::new(ident.span(), "duplicate `order` option"));
+                }
+
+                input.parse::<Token![=]>()?;
+                ret.order = Some(input.parse::<LitInt>()?.base10_parse()?);
+                parse_comma(input)?;
+                continue;
             }
-        } else {
-            Ok(Self::default())
+
+            if ident == "after" {
+                input.parse::<Token![=]>()?;
+                ret.after.push(input.parse()?);
+                parse_comma(input)?;
+                continue;
+            }
+
+            if ident == "bound" {
+                if ret.bound.is_some() {
+                    return Err(ParseError::new(ident.span(), "duplicate `bound` option"));
+                }
+
+                ret.bound = Some(parse_bound(input)?);
+                parse_comma(input)?;
+                continue;
+            }
+
+            let Some(mode) = FieldMode::parse_rest(&ident, input)? else {
+                return Err(ParseError::new(
+                    ident.span(),
+                    "expected `ignore`, `with`, `with_context`, `iter`, `iter_with`, \
+                     `concurrent`, `order`, `after`, or `bound`",
+                ));
+            };
+
+            if ret.mode.is_some() {
+                return Err(ParseError::new(
+                    ident.span(),
+                    "only one of `ignore`, `with`, `with_context`, `iter`, or `iter_with` may be \
+                     specified",
+                ));
+            }
+
+            ret.mode = Some(mode);
+            parse_comma(input)?;
         }
+
+        Ok(ret)
+    }
+}
+
+/// Parse the comma separating two options, if this is not the last option.
+pub fn parse_comma(input: ParseStream) -> ParseResult<()> {
+    if !input.is_empty() {
+        input.parse::<Token![,]>()?;
     }
+
+    Ok(())
 }
 
-pub fn parse_field_attrs<I: IntoIterator<Item = Attribute>>(
+/// Parse the `#[dispose]` attribute from a list of attributes into `A`, which
+/// is either a [`FieldAttr`] or an [`ItemAttr`](crate::item_attr::ItemAttr).
+pub fn parse_attrs<A: Parse + Default, I: IntoIterator<Item = Attribute>>(
     attrs: I,
     diag: &mut TokenStream,
-) -> ParseResult<Option<FieldAttr>> {
+) -> ParseResult<Option<A>> {
     let mut ret = Ok(None);
     let mut n = 0;
 
@@ -98,8 +168,16 @@ pub fn parse_field_attrs<I: IntoIterator<Item = Attribute>>(
 
                 ret = Err(ParseError::new(span, "Duplicate #[dispose] attribute"));
             } else {
-                // TODO: using ToTokens is stupid and you know it
-                ret = match Parser::parse2(FieldAttr::parse, attr.meta.to_token_stream()) {
+                let parsed = match &attr.meta {
+                    Meta::Path(_) => Ok(A::default()),
+                    Meta::List(l) => Parser::parse2(A::parse, l.tokens.clone()),
+                    Meta::NameValue(n) => Err(ParseError::new(
+                        n.eq_token.span,
+                        "expected #[dispose] or #[dispose(...)]",
+                    )),
+                };
+
+                ret = match parsed {
                     Ok(a) => Ok(Some(a)),
                     Err(e) => {
                         diag.extend(
@@ -0,0 +1,224 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use syn::{spanned::Spanned, Error, Member};
+
+use super::member_to_string;
+
+/// A field to be placed in disposal order.
+#[derive(Debug)]
+pub struct OrderNode<T> {
+    pub member: Member,
+    pub order: i32,
+    pub after: Vec<Member>,
+    /// Members referenced by this field's `with` expression, which must be
+    /// disposed after it.
+    pub uses: Vec<Member>,
+    /// Whether this field is ignored, in which case it is never moved and need
+    /// not be disposed after the fields that use it.
+    pub ignored: bool,
+    pub val: T,
+}
+
+/// Sort fields into the order they should be disposed in.
+///
+/// Fields start in declaration order (reversed if `reverse` is set), and are
+/// stably sorted by their `order` keys.  Any `after` constraints are then
+/// satisfied by a topological sort which otherwise preserves that order,
+/// along with the implicit constraint that any field used in another field's
+/// `with` expression is disposed after that field.
+///
+/// Every member named by `after` or `uses` is checked against the fields in
+/// `nodes`, and any that do not exist are reported as being missing from
+/// `ty_name`.
+pub fn sort<T>(nodes: Vec<OrderNode<T>>, reverse: bool, ty_name: &str) -> Result<Vec<T>, Error> {
+    check_members(&nodes, ty_name)?;
+
+    let mut base: Vec<usize> = (0..nodes.len()).collect();
+
+    if reverse {
+        base.reverse();
+    }
+
+    base.sort_by_key(|&i| nodes[i].order);
+
+    let mut rank = vec![0; nodes.len()];
+    for (r, &i) in base.iter().enumerate() {
+        rank[i] = r;
+    }
+
+    let mut succs = vec![vec![]; nodes.len()];
+    let mut preds = vec![vec![]; nodes.len()];
+
+    for (i, node) in nodes.iter().enumerate() {
+        for after in &node.after {
+            let j = nodes.iter().position(|n| n.member == *after).unwrap();
+
+            if i == j {
+                return Err(Error::new(
+                    after.span(),
+                    format!(
+                        "field `{}` cannot be disposed after itself",
+                        member_to_string(after.clone()),
+                    ),
+                ));
+            }
+
+            succs[j].push(i);
+            preds[i].push((j, after, false));
+        }
+
+        for used in &node.uses {
+            let j = nodes.iter().position(|n| n.member == *used).unwrap();
+
+            if nodes[j].ignored {
+                continue;
+            }
+
+            if i == j {
+                return Err(Error::new(
+                    used.span(),
+                    format!(
+                        "field `{}` cannot be used to dispose itself",
+                        member_to_string(used.clone()),
+                    ),
+                ));
+            }
+
+            succs[i].push(j);
+            preds[j].push((i, used, true));
+        }
+    }
+
+    let mut indeg: Vec<_> = preds.iter().map(Vec::len).collect();
+    let mut ready: BTreeSet<_> = (0..nodes.len())
+        .filter(|&i| indeg[i] == 0)
+        .map(|i| (rank[i], i))
+        .collect();
+    let mut sorted = Vec::with_capacity(nodes.len());
+
+    while let Some((_, i)) = ready.pop_first() {
+        sorted.push(i);
+
+        for &s in &succs[i] {
+            indeg[s] -= 1;
+
+            if indeg[s] == 0 {
+                ready.insert((rank[s], s));
+            }
+        }
+    }
+
+    if sorted.len() < nodes.len() {
+        return Err(cycle_error(&nodes, &preds, &indeg));
+    }
+
+    let mut vals: Vec<_> = nodes.into_iter().map(|n| Some(n.val)).collect();
+
+    Ok(sorted.into_iter().map(|i| vals[i].take().unwrap()).collect())
+}
+
+/// Produce an error describing one of the cycles among the nodes that could
+/// not be sorted.
+fn cycle_error<T>(
+    nodes: &[OrderNode<T>],
+    preds: &[Vec<(usize, &Member, bool)>],
+    indeg: &[usize],
+) -> Error {
+    let name = |i: usize| member_to_string(nodes[i].member.clone());
+
+    // Every unsorted node has an unsorted predecessor, so following them from
+    // any unsorted node must eventually revisit a node.
+    let start = indeg.iter().position(|&d| d > 0).unwrap();
+    let mut path = vec![start];
+
+    let cycle = loop {
+        let curr = *path.last().unwrap();
+        let (next, ..) = *preds[curr].iter().find(|(j, ..)| indeg[*j] > 0).unwrap();
+
+        if let Some(pos) = path.iter().position(|&i| i == next) {
+            break &path[pos..];
+        }
+
+        path.push(next);
+    };
+
+    let edge = |i: usize, j: usize| preds[i].iter().find(|(k, ..)| *k == j).unwrap();
+    let first = cycle[0];
+    let (_, span_member, _) = edge(first, cycle[1]);
+    let mut implicit = false;
+
+    let mut msg = format!("cyclic disposal order: `{}`", name(first));
+    for (k, &i) in cycle[1..].iter().chain([&first]).enumerate() {
+        implicit |= edge(cycle[k], i).2;
+        write!(msg, " after `{}`", name(i)).unwrap();
+    }
+
+    if implicit {
+        msg.push_str(
+            " (fields used in `with` expressions are disposed after the fields that use them)",
+        );
+    }
+
+    Error::new(span_member.span(), msg)
+}
+
+/// Report every member named by `after` or `uses` that is not a field.
+fn check_members<T>(nodes: &[OrderNode<T>], ty_name: &str) -> Result<(), Error> {
+    let mut err: Option<Error> = None;
+
+    for member in nodes.iter().flat_map(|n| n.after.iter().chain(&n.uses)) {
+        if nodes.iter().any(|n| n.member == *member) {
+            continue;
+        }
+
+        let name = member_to_string(member.clone());
+        let mut msg = format!("no field `{name}` on `{ty_name}`");
+
+        // Suggesting a different index for a tuple field isn't helpful
+        let suggestion = nodes
+            .iter()
+            .filter(|_| matches!(member, Member::Named(_)))
+            .map(|n| member_to_string(n.member.clone()))
+            .map(|f| (edit_distance(&name, &f), f))
+            .filter(|(d, f)| *d <= f.chars().count().max(name.chars().count()) / 3 + 1)
+            .min_by_key(|(d, _)| *d);
+
+        if let Some((_, field)) = suggestion {
+            write!(msg, "; did you mean `{field}`?").unwrap();
+        } else if !nodes.is_empty() {
+            let fields: Vec<_> = nodes
+                .iter()
+                .map(|n| format!("`{}`", member_to_string(n.member.clone())))
+                .collect();
+            write!(msg, "; available fields are {}", fields.join(", ")).unwrap();
+        }
+
+        let new = Error::new(member.span(), msg);
+
+        match err {
+            Some(ref mut e) => e.combine(new),
+            None => err = Some(new),
+        }
+    }
+
+    err.map_or(Ok(()), Err)
+}
+
+/// Compute the Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<_> = b.chars().collect();
+    let mut prev: Vec<_> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let sub = prev[j] + usize::from(ca != *cb);
+            curr.push(sub.min(prev[j + 1] + 1).min(curr[j] + 1));
+        }
+
+        prev = curr;
+    }
+
+    prev[b.len()]
+}
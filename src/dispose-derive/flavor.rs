@@ -0,0 +1,315 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote_spanned};
+use syn::{parse_quote, parse_quote_spanned, Expr, Generics, Ident, Lifetime, Type, WherePredicate};
+
+/// The trait being derived by a derive macro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    Dispose,
+    TryDispose,
+    AsyncDispose,
+}
+
+/// A single field disposal produced by [`Flavor::field_call`].
+#[derive(Debug)]
+pub struct FieldCall {
+    pub span: Span,
+    pub label: String,
+    pub call: TokenStream,
+    pub concurrent: bool,
+}
+
+impl Flavor {
+    pub fn trait_name(self) -> &'static str {
+        match self {
+            Self::Dispose => "Dispose",
+            Self::TryDispose => "TryDispose",
+            Self::AsyncDispose => "AsyncDispose",
+        }
+    }
+
+    /// Returns the trait and method used to dispose a field.
+    fn field_trait(self, span: Span, is_iter: bool, with: bool) -> (Ident, Ident) {
+        let (prefix, method_prefix) = match self {
+            Self::Dispose => ("", ""),
+            Self::TryDispose => ("Try", "try_"),
+            Self::AsyncDispose => ("Async", ""),
+        };
+
+        let (trait_name, method) = match (is_iter, with) {
+            (false, false) => ("Dispose", "dispose"),
+            (true, false) => ("DisposeIterator", "dispose_iter"),
+            (false, true) => ("DisposeWith", "dispose_with"),
+            (true, true) => ("DisposeIteratorWith", "dispose_iter_with"),
+        };
+
+        (
+            format_ident!("{}{}", prefix, trait_name, span = span),
+            format_ident!("{}{}", method_prefix, method, span = span),
+        )
+    }
+
+    /// Produce a call disposing a single field of type `ty` bound to `name`.
+    ///
+    /// For flavors that support dispose reasons, the call passes on the reason
+    /// bound to `__dispose_reason`.
+    pub fn field_call(
+        self,
+        span: Span,
+        ty: &Type,
+        name: &Ident,
+        is_iter: bool,
+        with: Option<Expr>,
+    ) -> TokenStream {
+        let (trait_name, method) = self.field_trait(span, is_iter, with.is_some());
+        let params = with.is_some().then(|| quote_spanned! { span => <_> });
+        let with = with.map(|w| quote_spanned! { span => , #w });
+
+        if self == Self::AsyncDispose {
+            return quote_spanned! { span =>
+                <#ty as ::dispose::#trait_name #params>::#method(#name #with)
+            };
+        }
+
+        let method = format_ident!("{}_because", method, span = span);
+
+        quote_spanned! { span =>
+            <#ty as ::dispose::#trait_name #params>::#method(#name #with, __dispose_reason)
+        }
+    }
+
+    /// Produce a call disposing a single field of type `ty` bound to `name`,
+    /// passing it a reference to the current dispose context of type `ctx`.
+    ///
+    /// Returns `None` for flavors that do not support dispose contexts.
+    pub fn context_call(
+        self,
+        span: Span,
+        ty: &Type,
+        name: &Ident,
+        ctx: &Type,
+    ) -> Option<TokenStream> {
+        let ctx_name = Ident::new("__dispose_ctx", span);
+        let call = self.field_call(span, ty, name, false, Some(parse_quote! { #ctx_name }));
+
+        match self {
+            Self::Dispose => Some(quote_spanned! { span =>
+                ::dispose::__private::expect_context::<#ctx, _>(|#ctx_name| #call)
+            }),
+            Self::TryDispose => Some(quote_spanned! { span =>
+                ::dispose::__private::try_context::<#ctx>(|#ctx_name| {
+                    ::dispose::__private::box_error(#call)
+                })
+            }),
+            Self::AsyncDispose => None,
+        }
+    }
+
+    /// Produce the where-clause predicates required by [`Self::field_call`]
+    /// for a field of type `ty`, disposed with a value of type `with` if one is
+    /// given.  If `with` contains a borrow, its lifetime should be given as
+    /// `lifetime`, which is bound by the resulting predicates.
+    pub fn field_bounds(
+        self,
+        span: Span,
+        ty: &Type,
+        is_iter: bool,
+        with: Option<&Type>,
+        lifetime: Option<&Lifetime>,
+    ) -> Vec<WherePredicate> {
+        let (trait_name, _) = self.field_trait(span, is_iter, with.is_some());
+        let binder = lifetime.map(|l| quote_spanned! { span => for<#l> });
+        let with = with.map(|w| quote_spanned! { span => <#w> });
+
+        vec![parse_quote_spanned! { span =>
+            #binder #ty: ::dispose::#trait_name #with
+        }]
+    }
+
+    /// Produce the where-clause predicates required by [`Self::context_call`]
+    /// for a field of type `ty` disposed with a dispose context of type `ctx`.
+    pub fn context_bounds(self, span: Span, ty: &Type, ctx: &Type) -> Vec<WherePredicate> {
+        let lifetime = Lifetime::new("'__dispose_ctx", span);
+        let with = parse_quote_spanned! { span => &#lifetime #ctx };
+
+        let mut preds = self.field_bounds(span, ty, false, Some(&with), Some(&lifetime));
+        preds.push(parse_quote_spanned! { span => #ctx: 'static });
+
+        preds
+    }
+
+    /// Combine the disposal calls for a set of fields into a sequence of
+    /// statements.
+    ///
+    /// Panics raised while disposing each field are caught and collected in
+    /// `__dispose_panics`, so that the remaining fields are still disposed.
+    pub fn sequence(self, span: Span, fields: impl IntoIterator<Item = FieldCall>) -> TokenStream {
+        let mut stmts = vec![];
+        let mut group = vec![];
+
+        for field in fields {
+            let FieldCall {
+                span,
+                label,
+                call,
+                concurrent,
+            } = field;
+
+            match self {
+                Self::Dispose => stmts.push(quote_spanned! { span =>
+                    __dispose_panics.run(|| #call)
+                }),
+                Self::TryDispose => stmts.push(quote_spanned! { span =>
+                    __dispose_panics.run(|| __dispose_errors.record(#label, #call))
+                }),
+                Self::AsyncDispose if concurrent => group.push((span, call)),
+                Self::AsyncDispose => {
+                    stmts.extend(join(&mut group));
+                    stmts.push(await_one(span, &call));
+                },
+            }
+        }
+
+        stmts.extend(join(&mut group));
+
+        quote_spanned! { span => #(#stmts;)* }
+    }
+
+    /// Produce the trait implementation given the body of the disposal
+    /// function.
+    pub fn impl_trait(
+        self,
+        span: Span,
+        name: &Ident,
+        generics: &Generics,
+        body: &TokenStream,
+    ) -> TokenStream {
+        let (impl_vars, ty_vars, where_clause) = generics.split_for_impl();
+
+        match self {
+            Self::Dispose => quote_spanned! { span =>
+                impl #impl_vars ::dispose::Dispose for #name #ty_vars #where_clause {
+                    fn dispose(self) {
+                        ::dispose::Dispose::dispose_because(
+                            self,
+                            ::dispose::DisposeReason::Explicit,
+                        );
+                    }
+
+                    #[allow(non_snake_case, redundant_semicolons, unused_mut, unused_variables)]
+                    fn dispose_because(self, __dispose_reason: ::dispose::DisposeReason) {
+                        let mut __dispose_panics = ::dispose::__private::Panics::new();
+
+                        #body
+
+                        __dispose_panics.resume();
+                    }
+                }
+            },
+            Self::TryDispose => quote_spanned! { span =>
+                impl #impl_vars ::dispose::TryDispose for #name #ty_vars #where_clause {
+                    type Error = ::dispose::DisposeErrors;
+
+                    fn try_dispose(self) -> ::core::result::Result<(), ::dispose::DisposeErrors> {
+                        ::dispose::TryDispose::try_dispose_because(
+                            self,
+                            ::dispose::DisposeReason::Explicit,
+                        )
+                    }
+
+                    #[allow(non_snake_case, redundant_semicolons, unused_mut, unused_variables)]
+                    fn try_dispose_because(
+                        self,
+                        __dispose_reason: ::dispose::DisposeReason,
+                    ) -> ::core::result::Result<(), ::dispose::DisposeErrors> {
+                        let mut __dispose_errors = ::dispose::DisposeErrors::new();
+                        let mut __dispose_panics = ::dispose::__private::Panics::new();
+
+                        #body
+
+                        __dispose_panics.resume();
+                        __dispose_errors.into_result()
+                    }
+                }
+            },
+            Self::AsyncDispose => quote_spanned! { span =>
+                impl #impl_vars ::dispose::AsyncDispose for #name #ty_vars #where_clause {
+                    #[allow(non_snake_case, redundant_semicolons, unused_mut)]
+                    async fn dispose(self) {
+                        let mut __dispose_panics = ::dispose::__private::Panics::new();
+
+                        #body
+
+                        __dispose_panics.resume();
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Await a single future, recording any panic it raises.
+fn await_one(span: Span, call: &TokenStream) -> TokenStream {
+    quote_spanned! { span =>
+        __dispose_panics.record(::dispose::__private::CatchUnwind::new(#call).await)
+    }
+}
+
+/// Await a group of futures concurrently, draining `group` and recording any
+/// panics they raise.
+fn join(group: &mut Vec<(Span, TokenStream)>) -> Option<TokenStream> {
+    match &**group {
+        [] => return None,
+        [(span, call)] => {
+            let ret = await_one(*span, call);
+            group.clear();
+            return Some(ret);
+        },
+        _ => (),
+    }
+
+    let span = group[0].0;
+    let (futs, outs): (Vec<_>, Vec<_>) = (0..group.len())
+        .map(|i| {
+            (
+                format_ident!("__dispose_fut_{}", i, span = span),
+                format_ident!("__dispose_out_{}", i, span = span),
+            )
+        })
+        .unzip();
+    let calls = group.drain(..).map(|(span, call)| {
+        quote_spanned! { span => ::dispose::__private::CatchUnwind::new(#call) }
+    });
+
+    Some(quote_spanned! { span =>
+        {
+            #(let mut #futs = ::core::pin::pin!(#calls);)*
+            #(let mut #outs = ::core::option::Option::None;)*
+
+            ::core::future::poll_fn(|__dispose_cx| {
+                #(
+                    if #outs.is_none() {
+                        if let ::core::task::Poll::Ready(r) =
+                            ::core::future::Future::poll(#futs.as_mut(), __dispose_cx)
+                        {
+                            #outs = ::core::option::Option::Some(r);
+                        }
+                    }
+                )*
+
+                if #(#outs.is_some())&&* {
+                    ::core::task::Poll::Ready(())
+                } else {
+                    ::core::task::Poll::Pending
+                }
+            })
+            .await;
+
+            #(
+                if let ::core::option::Option::Some(r) = #outs {
+                    __dispose_panics.record(r);
+                }
+            )*
+        }
+    })
+}
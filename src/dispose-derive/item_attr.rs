@@ -0,0 +1,85 @@
+use syn::{
+    ext::IdentExt,
+    parse::{Error as ParseError, Parse, ParseStream, Result as ParseResult},
+    Ident, Token, WherePredicate,
+};
+
+use super::{
+    bound::parse_bound,
+    field_attr::{parse_comma, FieldMode},
+};
+
+/// Options from a `#[dispose]` attribute on a struct, enum, or enum variant.
+#[derive(Debug, Clone, Default)]
+pub struct ItemAttr {
+    pub default: Option<FieldMode>,
+    pub reverse: Option<Ident>,
+    pub bound: Option<Vec<WherePredicate>>,
+}
+
+impl ItemAttr {
+    /// Apply the options of a nested item (i.e. an enum variant) over the
+    /// options of its container.  The container's `bound` is not inherited,
+    /// since it replaces the bounds of every variant.
+    pub fn inherit(self, parent: &Self) -> Self {
+        Self {
+            default: self.default.or_else(|| parent.default.clone()),
+            reverse: self.reverse.or_else(|| parent.reverse.clone()),
+            bound: self.bound,
+        }
+    }
+}
+
+impl Parse for ItemAttr {
+    fn parse(input: ParseStream) -> ParseResult<Self> {
+        let mut ret = Self::default();
+
+        while !input.is_empty() {
+            let ident = input.call(Ident::parse_any)?;
+
+            match ident {
+                ref i if i == "default" => {
+                    if ret.default.is_some() {
+                        return Err(ParseError::new(i.span(), "duplicate `default` option"));
+                    }
+
+                    input.parse::<Token![=]>()?;
+                    let mode = input.call(Ident::parse_any)?;
+
+                    let Some(mode) = FieldMode::parse_rest(&mode, input)? else {
+                        return Err(ParseError::new(
+                            mode.span(),
+                            "expected `ignore`, `with`, `with_context`, `iter`, or `iter_with`",
+                        ));
+                    };
+
+                    ret.default = Some(mode);
+                },
+                i if i == "reverse" => {
+                    if ret.reverse.is_some() {
+                        return Err(ParseError::new(i.span(), "duplicate `reverse` option"));
+                    }
+
+                    ret.reverse = Some(i);
+                },
+                i if i == "bound" => {
+                    if ret.bound.is_some() {
+                        return Err(ParseError::new(i.span(), "duplicate `bound` option"));
+                    }
+
+                    ret.bound = Some(parse_bound(input)?);
+                },
+                i => {
+                    return Err(ParseError::new(
+                        i.span(),
+                        "expected `default`, `reverse`, or `bound`",
+                    ))
+                },
+            }
+
+            parse_comma(input)?;
+        }
+
+        Ok(ret)
+    }
+}
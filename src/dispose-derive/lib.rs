@@ -12,24 +12,31 @@
 
 //! Derive macro for the `dispose` crate.
 //!
-//! This crate provides a derive macro for quickly deriving `Dispose` on types
-//! where the values can be consumed relatively trivially.
+//! This crate provides derive macros for quickly deriving `Dispose`,
+//! `TryDispose`, and `AsyncDispose` on types where the values can be consumed
+//! relatively trivially.
 
 use proc_macro::TokenStream as TokenStream1;
 use proc_macro2::{Span, TokenStream};
 use quote::quote_spanned;
 use syn::{
-    parse_macro_input, spanned::Spanned, Data, DataEnum, DataStruct, DeriveInput, Field, Fields,
-    Ident, Index, Member,
+    parse_macro_input, parse_quote_spanned, spanned::Spanned, Data, DataEnum, DataStruct,
+    DeriveInput, Field, Fields, Ident, Index, Lifetime, Member, Type,
 };
 
+mod bound;
 mod field_attr;
+mod field_order;
+mod flavor;
+mod item_attr;
 mod with_val;
-// mod item_attr;
 
-use field_attr::{parse_field_attrs, FieldMode};
+use bound::Bounds;
+use field_attr::{parse_attrs, FieldAttr, FieldMode};
+use field_order::OrderNode;
+use flavor::{FieldCall, Flavor};
+use item_attr::ItemAttr;
 use with_val::WithVal;
-// use item_attr::*;
 
 type Result<T, E = ()> = std::result::Result<T, E>;
 
@@ -42,8 +49,8 @@ type Result<T, E = ()> = std::result::Result<T, E>;
 /// # The `#[dispose]` attribute
 ///
 /// The `#[dispose]` attribute available to types deriving `Dispose` provides
-/// four options for decorating fields: `ignore`, `with`, `iter`, and
-/// `iter_with`.
+/// five options for decorating fields: `ignore`, `with`, `with_context`,
+/// `iter`, and `iter_with`.
 ///
 /// - `#[dispose(ignore)]` is the simplest option.  It disables generating a
 ///   `.dispose()` call for the field it decorates.
@@ -51,19 +58,284 @@ type Result<T, E = ()> = std::result::Result<T, E>;
 ///   `.dispose_with(...)` call that is provided with a value determined by
 ///   `<expr>`.  `expr` can take one of two forms: `.memb` for a member access
 ///   into `self`, or any other Rust expression, which will be token-pasted into
-///   the `dispose_with` call as-is.
+///   the `dispose_with` call as-is.  Any fields of `self` referenced by
+///   `<expr>` that are not ignored are disposed after the field using them, so
+///   a field can hold the context for its siblings (e.g.
+///   `#[dispose(with = &self.dev)]`) without being moved first.
+/// - `#[dispose(with_context = <type>)]` also changes the `.dispose()` call to
+///   a `.dispose_with(...)` call, but instead provides it with a `&<type>`
+///   retrieved from the current thread's dispose context, as set by
+///   `dispose::with_dispose_context`.  This avoids storing a reference to
+///   shared state such as a device in every container.  If no such context is
+///   set, the derived `Dispose` implementation panics, and the derived
+///   `TryDispose` implementation records a `MissingContext` error for the
+///   field.  This option requires the `std` feature of `dispose`, and is not
+///   supported when deriving [`AsyncDispose`], since the context cannot be
+///   held across an `.await`.
 /// - `#[dispose(iter)]` changes the `.dispose()` call to `.dispose_iter()`, for
 ///   types that implement `DisposeIterator` rather than `Dispose`.
 /// - `#[dispose(iter_with = <expr>)]` changes the `.dispose()` call to
 ///   `.dispose_iter_with(...)`, behaving similarly to both `#[dispose(iter)]`
 ///   and `#[dispose(with = <expr>)]`.
 ///
+/// Additional options may follow these, separated by commas:
+///
+/// - `concurrent`, which is only available when deriving [`AsyncDispose`].
+/// - `order = <integer>` sets the position of the field in the disposal order.
+///   Fields are disposed in ascending order of this key, and fields with the
+///   same key (including every field without one, which defaults to `0`) are
+///   disposed in declaration order.
+/// - `after = <field>` requires the field to be disposed after the named
+///   field (or, for tuple structs, the field at the given index).  This option
+///   may be given more than once, and takes precedence over `order`.
+/// - `bound = "<predicates>"` replaces the where-clause bounds inferred for
+///   the field.  See [Generic types](#generic-types) below.
+///
+/// Referencing a field that does not exist, or creating a cycle of `after`
+/// options, is a compile error.
+///
+/// ```
+/// use std::cell::RefCell;
+///
+/// use dispose::{prelude::*, Disposable, Dispose};
+///
+/// thread_local!(static LOG: RefCell<Vec<&'static str>> = RefCell::default());
+///
+/// struct Handle(&'static str);
+///
+/// impl Dispose for Handle {
+///     fn dispose(self) { LOG.with(|l| l.borrow_mut().push(self.0)); }
+/// }
+///
+/// #[derive(Dispose)]
+/// struct Swapchain {
+///     #[dispose(after = views)]
+///     swapchain: Handle,
+///     views: Handle,
+///     #[dispose(order = -1)]
+///     semaphores: Handle,
+/// }
+///
+/// Disposable::new(Swapchain {
+///     swapchain: Handle("swapchain"),
+///     views: Handle("views"),
+///     semaphores: Handle("semaphores"),
+/// });
+///
+/// assert_eq!(LOG.with(|l| l.take()), ["semaphores", "views", "swapchain"]);
+/// ```
+///
+/// # Container attributes
+///
+/// The `#[dispose]` attribute can also be placed on the type itself, with the
+/// following options, separated by commas:
+///
+/// - `#[dispose(default = <option>)]` sets the option used for any field
+///   without its own `#[dispose]` attribute.  `<option>` can be any of the
+///   field options above other than `concurrent`, e.g.
+///   `#[dispose(default = ignore)]` or `#[dispose(default = with = .dev)]`.
+/// - `#[dispose(reverse)]` disposes fields in reverse declaration order.  Any
+///   `order` and `after` options on the fields are applied afterwards.
+/// - `#[dispose(bound = "<predicates>")]` replaces every where-clause bound
+///   inferred for the fields of the type.  See
+///   [Generic types](#generic-types) below.
+///
+/// For enums, the same options can be placed on each variant, overriding the
+/// options given on the enum for the fields of that variant.  A `bound` given
+/// on the enum applies to the whole implementation, and overrides any `bound`
+/// given on its variants.
+///
+/// ```
+/// use std::cell::RefCell;
+///
+/// use dispose::{prelude::*, Disposable, Dispose};
+///
+/// thread_local!(static LOG: RefCell<Vec<&'static str>> = RefCell::default());
+///
+/// struct Device;
+/// struct Handle(&'static str);
+///
+/// impl DisposeWith<&Device> for Handle {
+///     fn dispose_with(self, _: &Device) { LOG.with(|l| l.borrow_mut().push(self.0)); }
+/// }
+///
+/// #[derive(Dispose)]
+/// #[dispose(default = with = .dev, reverse)]
+/// struct Pipeline<'a> {
+///     #[dispose(ignore)]
+///     dev: &'a Device,
+///     layout: Handle,
+///     shader: Handle,
+/// }
+///
+/// #[derive(Dispose)]
+/// #[dispose(default = ignore)]
+/// enum Slot {
+///     Empty(&'static str),
+///     #[dispose(default = with = &Device)]
+///     Full(Handle),
+/// }
+///
+/// Disposable::new(Pipeline {
+///     dev: &Device,
+///     layout: Handle("layout"),
+///     shader: Handle("shader"),
+/// });
+/// Disposable::new(Slot::Full(Handle("slot")));
+/// Disposable::new(Slot::Empty("ignored"));
+///
+/// assert_eq!(LOG.with(|l| l.take()), ["shader", "layout", "slot"]);
+/// ```
+///
+/// # Generic types
+///
+/// For types with generic parameters, the derived implementation requires
+/// every disposed field whose type mentions a type parameter to implement
+/// the trait used to dispose it.  For example, a `Vec<T>` field adds the bound
+/// `Vec<T>: Dispose`, a `T` field marked with `#[dispose(with = .dev)]` adds
+/// `T: DisposeWith<D>` where `D` is the type of the `dev` field, and a `T`
+/// field marked with `#[dispose(with_context = C)]` adds
+/// `for<'a> T: DisposeWith<&'a C>`.  No bound is inferred for a field using
+/// any `with` expression other than a field of `self` (or a shared reference
+/// to one), since the type of the value it produces is unknown.
+///
+/// These bounds can be replaced with the `bound` option, either for a single
+/// field or for the whole type.  This is necessary for recursive types, whose
+/// inferred bounds would otherwise require themselves.  An empty string, as
+/// in `bound = ""`, removes the bounds altogether.
+///
+/// ```
+/// use std::cell::RefCell;
+///
+/// use dispose::{prelude::*, Disposable, Dispose};
+///
+/// thread_local!(static LOG: RefCell<Vec<&'static str>> = RefCell::default());
+///
+/// struct Handle(&'static str);
+///
+/// impl Dispose for Handle {
+///     fn dispose(self) { LOG.with(|l| l.borrow_mut().push(self.0)); }
+/// }
+///
+/// // Implements Dispose where Vec<T>: Dispose
+/// #[derive(Dispose)]
+/// struct Pool<T> {
+///     items: Vec<T>,
+/// }
+///
+/// // Inferring the bound Vec<Tree<T>>: Dispose would require Tree<T>: Dispose
+/// #[derive(Dispose)]
+/// #[dispose(bound = "T: Dispose")]
+/// struct Tree<T> {
+///     val: T,
+///     children: Vec<Tree<T>>,
+/// }
+///
+/// Disposable::new(Pool {
+///     items: vec![Handle("a"), Handle("b")],
+/// });
+/// Disposable::new(Tree {
+///     val: Handle("root"),
+///     children: vec![Tree {
+///         val: Handle("leaf"),
+///         children: vec![],
+///     }],
+/// });
+///
+/// assert_eq!(LOG.with(|l| l.take()), ["a", "b", "root", "leaf"]);
+/// ```
+///
+/// # Dispose reasons
+///
+/// The derived implementation overrides `dispose_because`, passing the
+/// `DisposeReason` it is given on to every field, so a field can tell whether
+/// its container was dropped normally or during a panic.  Calling `dispose`
+/// directly disposes each field with a reason of `Explicit`.
+///
+/// ```
+/// use std::{cell::RefCell, panic::catch_unwind};
+///
+/// use dispose::{prelude::*, Disposable, Dispose, DisposeReason, ReasonFn};
+///
+/// thread_local!(static LOG: RefCell<Vec<DisposeReason>> = RefCell::default());
+///
+/// fn log(reason: DisposeReason) { LOG.with(|l| l.borrow_mut().push(reason)); }
+///
+/// #[derive(Dispose)]
+/// struct Transaction {
+///     rollback: ReasonFn<fn(DisposeReason)>,
+///     #[dispose(iter)]
+///     savepoints: Vec<ReasonFn<fn(DisposeReason)>>,
+/// }
+///
+/// let tx = || Transaction {
+///     rollback: ReasonFn(log),
+///     savepoints: vec![ReasonFn(log)],
+/// };
+///
+/// drop(Disposable::new(tx()));
+/// assert!(catch_unwind(|| {
+///     let _tx = Disposable::new(tx());
+///     panic!("oh no");
+/// })
+/// .is_err());
+///
+/// assert_eq!(LOG.with(|l| l.take()), [
+///     DisposeReason::ScopeExit,
+///     DisposeReason::ScopeExit,
+///     DisposeReason::Unwind,
+///     DisposeReason::Unwind,
+/// ]);
+/// ```
+///
+/// # Panics
+///
+/// If disposing a field panics, the remaining fields are still disposed before
+/// the first panic is resumed, similarly to how Rust drops the remaining fields
+/// of a struct when one of their destructors panics.
+///
+/// ```
+/// use std::{cell::RefCell, panic::catch_unwind};
+///
+/// use dispose::{prelude::*, Disposable, Dispose};
+///
+/// thread_local!(static LOG: RefCell<Vec<&'static str>> = RefCell::default());
+///
+/// struct Handle(&'static str);
+///
+/// impl Dispose for Handle {
+///     fn dispose(self) {
+///         assert!(self.0 != "bad", "failed to release handle");
+///         LOG.with(|l| l.borrow_mut().push(self.0));
+///     }
+/// }
+///
+/// #[derive(Dispose)]
+/// struct Handles {
+///     first: Handle,
+///     bad: Handle,
+///     #[dispose(iter)]
+///     rest: Vec<Handle>,
+/// }
+///
+/// let res = catch_unwind(|| {
+///     Disposable::new(Handles {
+///         first: Handle("first"),
+///         bad: Handle("bad"),
+///         rest: vec![Handle("second"), Handle("bad"), Handle("third")],
+///     });
+/// });
+///
+/// assert!(res.is_err());
+/// assert_eq!(LOG.with(|l| l.take()), ["first", "second", "third"]);
+/// ```
+///
 /// # Examples
 ///
 /// Here's a dead-simple example:
 ///
 /// ```
-/// use dispose::{prelude::*, Disposable};
+/// use dispose::{prelude::*, Disposable, Dispose};
 ///
 /// struct MyResource {
 ///     important_stuff: String,
@@ -177,11 +449,11 @@ type Result<T, E = ()> = std::result::Result<T, E>;
 /// #
 /// # // Actually allocating these resources is beyond the scope of a documentation example, but I
 /// # // did want to make this both realistic and doctest-able.
-/// # fn create_buffer(_: &gfx_backend_empty::Device) -> Buffer<gfx_backend_empty::Backend> {
-/// #     Buffer(())
+/// # fn create_buffer(dev: &gfx_backend_empty::Device) -> Buffer<gfx_backend_empty::Backend> {
+/// #     Buffer(unsafe { dev.create_buffer(16, gfx_hal::buffer::Usage::empty()) }.unwrap())
 /// # }
-/// # fn alloc_memory(_: &gfx_backend_empty::Device) -> Memory<gfx_backend_empty::Backend> {
-/// #     Memory(())
+/// # fn alloc_memory(dev: &gfx_backend_empty::Device) -> Memory<gfx_backend_empty::Backend> {
+/// #     Memory(unsafe { dev.allocate_memory(gfx_hal::MemoryTypeId(0), 256) }.unwrap())
 /// # }
 /// #
 /// # let a_device = &gfx_backend_empty::Device;
@@ -206,10 +478,132 @@ type Result<T, E = ()> = std::result::Result<T, E>;
 /// // Draw cool things with the buffers here...
 /// # let _ = (buf, bufs); // Silence any unused warnings.
 /// ```
+///
+/// [`AsyncDispose`]: ./derive.AsyncDispose.html
 #[proc_macro_derive(Dispose, attributes(dispose))]
 pub fn derive_dispose(item: TokenStream1) -> TokenStream1 {
     let mut diag = TokenStream::new();
-    match derive_dispose_impl(parse_macro_input!(item), &mut diag) {
+    match derive_dispose_impl(parse_macro_input!(item), Flavor::Dispose, &mut diag) {
+        Ok(s) => [diag, s].into_iter().collect::<TokenStream>().into(),
+        Err(()) => diag.into(),
+    }
+}
+
+/// Add `TryDispose` support to a struct or enum where the contained values
+/// implement `TryDispose` or `TryDisposeWith<W>`.
+///
+/// This macro is the fallible equivalent of the [`Dispose`] derive macro, and
+/// accepts the same `#[dispose]` field attributes.  Fields are disposed with
+/// `TryDispose`, `TryDisposeWith`, `TryDisposeIterator`, and
+/// `TryDisposeIteratorWith` respectively, and since all types implementing
+/// `Dispose` and `DisposeWith` also implement their fallible counterparts,
+/// infallible fields can be mixed freely with fallible ones.
+///
+/// Every field is disposed even if an earlier field fails.  The derived
+/// implementation uses `DisposeErrors` as its error type, which lists each
+/// failing field by name.  As with the [`Dispose`] derive macro, the reason
+/// given to `try_dispose_because` is passed on to every field.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{prelude::*, Disposable};
+///
+/// struct Flush(Result<(), &'static str>);
+///
+/// impl TryDispose for Flush {
+///     type Error = &'static str;
+///
+///     fn try_dispose(self) -> Result<(), &'static str> { self.0 }
+/// }
+///
+/// #[derive(TryDispose)]
+/// struct Writer {
+///     header: Flush,
+///     body: Flush,
+///     #[dispose(iter)]
+///     chunks: Vec<Flush>,
+///     on_close: fn(),
+/// }
+///
+/// let writer = Disposable::new(Writer {
+///     header: Flush(Err("broken pipe")),
+///     body: Flush(Ok(())),
+///     chunks: vec![Flush(Ok(())), Flush(Err("disk full"))],
+///     on_close: || println!("closed"),
+/// });
+///
+/// let errors = Disposable::try_dispose(writer).unwrap_err();
+/// let failed: Vec<_> = errors.iter().map(|(name, _)| name).collect();
+///
+/// assert_eq!(failed, ["header", "chunks"]);
+/// ```
+///
+/// [`Dispose`]: ./derive.Dispose.html
+#[proc_macro_derive(TryDispose, attributes(dispose))]
+pub fn derive_try_dispose(item: TokenStream1) -> TokenStream1 {
+    let mut diag = TokenStream::new();
+    match derive_dispose_impl(parse_macro_input!(item), Flavor::TryDispose, &mut diag) {
+        Ok(s) => [diag, s].into_iter().collect::<TokenStream>().into(),
+        Err(()) => diag.into(),
+    }
+}
+
+/// Add `AsyncDispose` support to a struct or enum where the contained values
+/// implement `AsyncDispose` or `AsyncDisposeWith<W>`.
+///
+/// This macro is the asynchronous equivalent of the [`Dispose`] derive macro,
+/// and accepts the same `#[dispose]` field attributes.  Fields are disposed with
+/// `AsyncDispose`, `AsyncDisposeWith`, `AsyncDisposeIterator`, and
+/// `AsyncDisposeIteratorWith` respectively, and each field's disposal is
+/// awaited in declaration order.
+///
+/// # Concurrent disposal
+///
+/// Fields whose teardown does not depend on one another can be marked with
+/// `#[dispose(concurrent)]`, which may be combined with any other option (e.g.
+/// `#[dispose(iter, concurrent)]`).  Each run of consecutive concurrent fields
+/// is awaited together, and the next non-concurrent field is not disposed until
+/// every field in the run has finished.  Note that any values referenced by a
+/// `with` expression are still passed by value, so concurrent fields should
+/// only share `Copy` contexts (such as references).
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{block_on, prelude::*, AsyncDispose, AsyncDisposable};
+///
+/// struct Socket(&'static str);
+///
+/// impl AsyncDispose for Socket {
+///     async fn dispose(self) { println!("closing {}", self.0); }
+/// }
+///
+/// #[derive(AsyncDispose)]
+/// struct Session {
+///     #[dispose(concurrent)]
+///     control: Socket,
+///     #[dispose(concurrent)]
+///     data: Socket,
+///     #[dispose(iter)]
+///     peers: Vec<Socket>,
+/// }
+///
+/// let session = AsyncDisposable::new(Session {
+///     control: Socket("control"),
+///     data: Socket("data"),
+///     peers: vec![Socket("peer 1"), Socket("peer 2")],
+/// });
+///
+/// // Closes the control and data sockets together, then each peer in order
+/// block_on(AsyncDisposable::dispose(session));
+/// ```
+///
+/// [`Dispose`]: ./derive.Dispose.html
+#[proc_macro_derive(AsyncDispose, attributes(dispose))]
+pub fn derive_async_dispose(item: TokenStream1) -> TokenStream1 {
+    let mut diag = TokenStream::new();
+    match derive_dispose_impl(parse_macro_input!(item), Flavor::AsyncDispose, &mut diag) {
         Ok(s) => [diag, s].into_iter().collect::<TokenStream>().into(),
         Err(()) => diag.into(),
     }
@@ -234,88 +628,181 @@ fn member_to_string(member: Member) -> String {
     }
 }
 
-fn derive_dispose_impl(input: DeriveInput, diag: &mut TokenStream) -> Result<TokenStream> {
+fn derive_dispose_impl(
+    input: DeriveInput,
+    flavor: Flavor,
+    diag: &mut TokenStream,
+) -> Result<TokenStream> {
     let span = input.span();
     let name = input.ident;
 
-    for attr in input.attrs {
-        if attr.path().is_ident("dispose") {
-            diag.extend(
-                syn::Error::new(span.unwrap().into(), "Unexpected #[dispose] attribute")
-                    .to_compile_error(),
-            );
-        }
-    }
-
-    let generics = input.generics;
-    let (impl_vars, ty_vars, where_clause) = generics.split_for_impl();
-
-    let default_mode = FieldMode::Dispose { is_iter: false };
+    let item_attr = parse_attrs::<ItemAttr, _>(input.attrs, diag)
+        .map_err(|_| ())?
+        .unwrap_or_default();
 
-    let fn_body = match input.data {
-        Data::Struct(s) => derive_dispose_struct(span, &default_mode, s, diag),
-        Data::Enum(e) => derive_dispose_enum(span, &default_mode, e, diag),
+    let (fn_body, bounds) = match input.data {
+        Data::Struct(s) => derive_dispose_struct(span, flavor, &name, &item_attr, s, diag),
+        Data::Enum(e) => derive_dispose_enum(span, flavor, &name, &item_attr, e, diag),
         Data::Union(_) => {
             diag.extend(
-                syn::Error::new(span.unwrap().into(), "Cannot derive Dispose on a union.")
-                    .to_compile_error(),
+                syn::Error::new(
+                    span.unwrap().into(),
+                    format!("Cannot derive {} on a union.", flavor.trait_name()),
+                )
+                .to_compile_error(),
             );
 
             Err(())
         },
     }?;
 
-    Ok(quote_spanned! { span =>
-        impl #impl_vars ::dispose::Dispose for #name #ty_vars #where_clause {
-            #[allow(non_snake_case, redundant_semicolons)]
-            fn dispose(self) {
-                #fn_body
-            }
+    let bounds = match item_attr.bound {
+        Some(b) => Bounds::explicit(b),
+        None => bounds,
+    };
+
+    let mut generics = input.generics;
+    bounds.apply(&mut generics);
+
+    Ok(flavor.impl_trait(span, &name, &generics, &fn_body))
+}
+
+/// The type (and, for enums, the variant) whose fields are being disposed.
+#[derive(Debug, Clone, Copy)]
+struct Owner<'a> {
+    ty: &'a Ident,
+    variant: Option<&'a str>,
+}
+
+impl Owner<'_> {
+    fn path(self) -> String {
+        match self.variant {
+            Some(v) => format!("{}::{v}", self.ty),
+            None => self.ty.to_string(),
         }
+    }
+}
+
+/// Determine the type of the value passed to `dispose_with` by a `with`
+/// option, along with the lifetime of any borrow it contains.
+///
+/// Bounds can only be inferred if this type is known, i.e. if the value is one
+/// of the fields in `types`.
+fn with_type(
+    span: Span,
+    with: &WithVal,
+    types: &[(Member, Type)],
+) -> Option<(Type, Option<Lifetime>)> {
+    let (member, borrowed) = with.self_member()?;
+    let (_, ty) = types.iter().find(|(m, _)| *m == member)?;
+
+    Some(if borrowed {
+        let lifetime = Lifetime::new("'__dispose_with", span);
+        (parse_quote_spanned! { span => &#lifetime #ty }, Some(lifetime))
+    } else {
+        (ty.clone(), None)
     })
 }
 
 fn dispose_fields(
     span: Span,
-    default_mode: &FieldMode,
+    flavor: Flavor,
+    item_attr: &ItemAttr,
     fields: Fields,
     diag: &mut TokenStream,
+    owner: Owner,
     field_name: impl Fn(Span, Member) -> Ident + Copy,
-) -> Result<TokenStream> {
-    let handle_field = |(id, field): (usize, Field)| {
+) -> Result<(TokenStream, Bounds)> {
+    let types: Vec<_> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (field_to_member(i, f), f.ty.clone()))
+        .collect();
+    let mut bounds = Bounds::default();
+
+    let mut handle_field = |(id, field): (usize, Field)| {
         let span = field.span();
-        let name = field_name(span, field_to_member(id, &field));
-
-        let attr = parse_field_attrs(field.attrs, diag).map_err(|_| ())?;
+        let member = field_to_member(id, &field);
+        let label = match owner.variant {
+            Some(p) => format!("{p}.{}", member_to_string(member.clone())),
+            None => member_to_string(member.clone()),
+        };
+        let name = field_name(span, member.clone());
+
+        let attr = parse_attrs::<FieldAttr, _>(field.attrs, diag)
+            .map_err(|_| ())?
+            .unwrap_or_default();
         let ty = field.ty;
 
-        Ok(match attr.map_or(default_mode.clone(), |a| a.mode) {
+        if let Some(ref conc) = attr.concurrent {
+            if flavor != Flavor::AsyncDispose {
+                diag.extend(
+                    syn::Error::new(
+                        conc.span(),
+                        "`concurrent` is only supported when deriving AsyncDispose",
+                    )
+                    .to_compile_error(),
+                );
+            }
+        }
+
+        let mode = attr.mode.or_else(|| item_attr.default.clone());
+
+        let mut uses = vec![];
+        let mut preds = vec![];
+
+        let call = match mode.unwrap_or_default() {
             FieldMode::Dispose { is_iter } => {
-                if is_iter {
-                    quote_spanned! { span =>
-                        <#ty as ::dispose::DisposeIterator>::dispose_iter(#name)
-                    }
-                } else {
-                    quote_spanned! { span =>
-                        <#ty as ::dispose::Dispose>::dispose(#name)
-                    }
-                }
+                preds = flavor.field_bounds(span, &ty, is_iter, None, None);
+
+                Some(flavor.field_call(span, &ty, &name, is_iter, None))
             },
             FieldMode::DisposeWith { is_iter, with } => {
-                let with = with.expand(field_name);
-
-                if is_iter {
-                    quote_spanned! { span =>
-                        <#ty as ::dispose::DisposeIteratorWith<_>>
-                            ::dispose_iter_with(#name, #with)
-                    }
-                } else {
-                    quote_spanned! { span =>
-                        <#ty as ::dispose::DisposeWith<_>>::dispose_with(#name, #with)
-                    }
+                if let Some((with_ty, lt)) = with_type(span, &with, &types) {
+                    preds = flavor.field_bounds(span, &ty, is_iter, Some(&with_ty), lt.as_ref());
+                }
+
+                let (with, members) = with.expand(field_name);
+                uses = members;
+
+                Some(flavor.field_call(span, &ty, &name, is_iter, Some(with)))
+            },
+            FieldMode::DisposeWithContext(ctx) => {
+                preds = flavor.context_bounds(span, &ty, &ctx);
+                let call = flavor.context_call(span, &ty, &name, &ctx);
+
+                if call.is_none() {
+                    diag.extend(
+                        syn::Error::new(
+                            ctx.span(),
+                            "`with_context` is not supported when deriving AsyncDispose",
+                        )
+                        .to_compile_error(),
+                    );
                 }
+
+                call
             },
-            FieldMode::Ignore => quote_spanned! { span => },
+            FieldMode::Ignore => None,
+        };
+
+        match attr.bound {
+            Some(b) => bounds.append(Bounds::explicit(b)),
+            None => bounds.infer(preds),
+        }
+
+        Ok(OrderNode {
+            member,
+            order: attr.order.unwrap_or(0),
+            after: attr.after,
+            uses,
+            ignored: call.is_none(),
+            val: call.map(|call| FieldCall {
+                span,
+                label,
+                call,
+                concurrent: attr.concurrent.is_some(),
+            }),
         })
     };
 
@@ -324,18 +811,25 @@ fn dispose_fields(
             .named
             .into_iter()
             .enumerate()
-            .map(handle_field)
+            .map(&mut handle_field)
             .collect::<Result<Vec<_>>>()?,
         Fields::Unnamed(u) => u
             .unnamed
             .into_iter()
             .enumerate()
-            .map(handle_field)
+            .map(&mut handle_field)
             .collect::<Result<Vec<_>>>()?,
         Fields::Unit => vec![],
     };
 
-    Ok(quote_spanned! { span => #(#fields;)* })
+    let fields = field_order::sort(fields, item_attr.reverse.is_some(), &owner.path())
+        .map_err(|e| diag.extend(e.to_compile_error()))?;
+
+    if let Some(ref b) = item_attr.bound {
+        bounds = Bounds::explicit(b.clone());
+    }
+
+    Ok((flavor.sequence(span, fields.into_iter().flatten()), bounds))
 }
 
 fn destructure_fields(
@@ -369,10 +863,12 @@ fn destructure_fields(
 
 fn derive_dispose_struct(
     span: Span,
-    default_mode: &FieldMode,
+    flavor: Flavor,
+    ty: &Ident,
+    item_attr: &ItemAttr,
     data: DataStruct,
     diag: &mut TokenStream,
-) -> Result<TokenStream> {
+) -> Result<(TokenStream, Bounds)> {
     fn field_name(span: Span, member: Member) -> Ident {
         Ident::new(
             &format!("__dispose_self_f{}", member_to_string(member)),
@@ -381,21 +877,28 @@ fn derive_dispose_struct(
     }
 
     let names = destructure_fields(span, &data.fields, field_name);
-    let fields = dispose_fields(span, default_mode, data.fields, diag, field_name)?;
+    let owner = Owner { ty, variant: None };
+    let (fields, bounds) =
+        dispose_fields(span, flavor, item_attr, data.fields, diag, owner, field_name)?;
 
-    Ok(quote_spanned! { span =>
-        let Self #names = self;
+    Ok((
+        quote_spanned! { span =>
+            let Self #names = self;
 
-        #fields
-    })
+            #fields
+        },
+        bounds,
+    ))
 }
 
 fn derive_dispose_enum(
     span: Span,
-    default_mode: &FieldMode,
+    flavor: Flavor,
+    ty: &Ident,
+    item_attr: &ItemAttr,
     data: DataEnum,
     diag: &mut TokenStream,
-) -> Result<TokenStream> {
+) -> Result<(TokenStream, Bounds)> {
     fn field_name(span: Span, member: Member, var: impl AsRef<str>) -> Ident {
         Ident::new(
             &format!(
@@ -407,17 +910,33 @@ fn derive_dispose_enum(
         )
     }
 
+    let mut bounds = Bounds::default();
+
     let variants = data
         .variants
         .into_iter()
         .map(|var| {
             let name = var.ident;
             let name_str = name.to_string();
+            let var_attr = parse_attrs::<ItemAttr, _>(var.attrs, diag)
+                .map_err(|_| ())?
+                .unwrap_or_default()
+                .inherit(item_attr);
 
             let names = destructure_fields(span, &var.fields, |i, f| field_name(i, f, &name_str));
-            let fields = dispose_fields(span, default_mode, var.fields, diag, |i, f| {
-                field_name(i, f, &name_str)
-            })?;
+            let (fields, var_bounds) = dispose_fields(
+                span,
+                flavor,
+                &var_attr,
+                var.fields,
+                diag,
+                Owner {
+                    ty,
+                    variant: Some(&name_str),
+                },
+                |i, f| field_name(i, f, &name_str),
+            )?;
+            bounds.append(var_bounds);
 
             Ok(quote_spanned! { span =>
                 Self::#name #names => {
@@ -427,9 +946,12 @@ fn derive_dispose_enum(
         })
         .collect::<Result<Vec<_>>>()?;
 
-    Ok(quote_spanned! { span =>
-        match self {
-            #(#variants),*
-        }
-    })
+    Ok((
+        quote_spanned! { span =>
+            match self {
+                #(#variants),*
+            }
+        },
+        bounds,
+    ))
 }
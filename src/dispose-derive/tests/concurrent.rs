@@ -0,0 +1,158 @@
+use std::{
+    cell::RefCell,
+    future::{poll_fn, Future},
+    pin::pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use dispose::AsyncDispose;
+
+type Log = Rc<RefCell<Vec<String>>>;
+
+/// A resource that logs when its disposal starts and ends, and which cannot
+/// finish disposing until the disposal of `waits_for` has started.
+struct Step {
+    log: Log,
+    name: &'static str,
+    waits_for: Option<&'static str>,
+}
+
+impl Step {
+    fn new(log: &Log, name: &'static str, waits_for: Option<&'static str>) -> Self {
+        Self {
+            log: Rc::clone(log),
+            name,
+            waits_for,
+        }
+    }
+}
+
+impl AsyncDispose for Step {
+    async fn dispose(self) {
+        self.log.borrow_mut().push(format!("start {}", self.name));
+
+        if let Some(other) = self.waits_for {
+            let started = format!("start {other}");
+
+            poll_fn(|_| {
+                if self.log.borrow().contains(&started) {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+        }
+
+        self.log.borrow_mut().push(format!("end {}", self.name));
+    }
+}
+
+/// Poll `fut` to completion, panicking if it stops making progress.
+fn run(fut: impl Future<Output = ()>) {
+    let mut fut = pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+
+    for _ in 0..100 {
+        if fut.as_mut().poll(&mut cx).is_ready() {
+            return;
+        }
+    }
+
+    panic!("disposal deadlocked");
+}
+
+#[derive(AsyncDispose)]
+struct Pair {
+    #[dispose(concurrent)]
+    a: Step,
+    #[dispose(concurrent)]
+    b: Step,
+}
+
+#[derive(AsyncDispose)]
+struct Runs {
+    #[dispose(concurrent)]
+    a: Step,
+    #[dispose(concurrent)]
+    b: Step,
+    c: Step,
+    #[dispose(concurrent)]
+    d: Step,
+    #[dispose(concurrent)]
+    e: Step,
+}
+
+#[derive(AsyncDispose)]
+enum Slot {
+    Empty,
+    Full(#[dispose(concurrent)] Step, #[dispose(concurrent)] Step),
+}
+
+#[test]
+fn concurrent_fields_overlap() {
+    let log = Log::default();
+
+    run(Pair {
+        a: Step::new(&log, "a", Some("b")),
+        b: Step::new(&log, "b", Some("a")),
+    }
+    .dispose());
+
+    assert_eq!(*log.borrow(), ["start a", "start b", "end b", "end a"]);
+}
+
+#[test]
+fn non_concurrent_field_breaks_run() {
+    let log = Log::default();
+
+    run(Runs {
+        a: Step::new(&log, "a", Some("b")),
+        b: Step::new(&log, "b", Some("a")),
+        c: Step::new(&log, "c", None),
+        d: Step::new(&log, "d", Some("e")),
+        e: Step::new(&log, "e", Some("d")),
+    }
+    .dispose());
+
+    assert_eq!(
+        *log.borrow(),
+        [
+            "start a", "start b", "end b", "end a", "start c", "end c", "start d", "start e",
+            "end e", "end d",
+        ]
+    );
+}
+
+#[test]
+#[should_panic = "disposal deadlocked"]
+fn non_concurrent_fields_are_sequential() {
+    #[derive(AsyncDispose)]
+    struct Sequential {
+        a: Step,
+        b: Step,
+    }
+
+    let log = Log::default();
+
+    run(Sequential {
+        a: Step::new(&log, "a", Some("b")),
+        b: Step::new(&log, "b", Some("a")),
+    }
+    .dispose());
+}
+
+#[test]
+fn concurrent_variant_fields() {
+    let log = Log::default();
+
+    run(Slot::Full(
+        Step::new(&log, "0", Some("1")),
+        Step::new(&log, "1", Some("0")),
+    )
+    .dispose());
+    run(Slot::Empty.dispose());
+
+    assert_eq!(*log.borrow(), ["start 0", "start 1", "end 1", "end 0"]);
+}
@@ -0,0 +1,216 @@
+use std::{
+    cell::RefCell,
+    panic::{catch_unwind, AssertUnwindSafe},
+    rc::Rc,
+};
+
+use dispose::{
+    Disposable, Dispose, DisposeReason, DisposeStack, DisposeWith, DynDisposable, TryDispose,
+    TryDisposeWith,
+};
+
+type Log = Rc<RefCell<Vec<(&'static str, DisposeReason)>>>;
+
+/// A resource that logs the reason it was disposed with.
+struct Res(Log, &'static str);
+
+impl Dispose for Res {
+    fn dispose(self) { self.dispose_because(DisposeReason::Explicit) }
+
+    fn dispose_because(self, reason: DisposeReason) { self.0.borrow_mut().push((self.1, reason)); }
+}
+
+/// A resource that logs the reason it was disposed with, requiring a context.
+struct WithRes(Log, &'static str);
+
+impl DisposeWith<&()> for WithRes {
+    fn dispose_with(self, ctx: &()) { self.dispose_with_because(ctx, DisposeReason::Explicit) }
+
+    fn dispose_with_because(self, _: &(), reason: DisposeReason) {
+        self.0.borrow_mut().push((self.1, reason));
+    }
+}
+
+/// A fallible resource that logs the reason it was disposed with.
+struct TryRes(Log, &'static str);
+
+impl TryDispose for TryRes {
+    type Error = &'static str;
+
+    fn try_dispose(self) -> Result<(), &'static str> {
+        self.try_dispose_because(DisposeReason::Explicit)
+    }
+
+    fn try_dispose_because(self, reason: DisposeReason) -> Result<(), &'static str> {
+        self.0.borrow_mut().push((self.1, reason));
+        Ok(())
+    }
+}
+
+impl TryDisposeWith<&()> for TryRes {
+    type Error = &'static str;
+
+    fn try_dispose_with(self, _: &()) -> Result<(), &'static str> { self.try_dispose() }
+
+    fn try_dispose_with_because(self, _: &(), reason: DisposeReason) -> Result<(), &'static str> {
+        self.try_dispose_because(reason)
+    }
+}
+
+#[derive(Dispose)]
+struct Inner {
+    res: Res,
+    #[dispose(with = &())]
+    with: WithRes,
+}
+
+#[derive(Dispose)]
+struct Outer {
+    inner: Inner,
+    #[dispose(iter)]
+    items: Vec<Res>,
+    #[dispose(iter_with = &())]
+    with_items: Vec<WithRes>,
+    stack: DisposeStack<'static>,
+    tuple: (&'static (), WithRes),
+}
+
+#[derive(Dispose)]
+enum Slot {
+    Full(Outer),
+}
+
+#[derive(TryDispose)]
+struct TryOuter {
+    res: TryRes,
+    #[dispose(with = &())]
+    with: TryRes,
+    #[dispose(iter)]
+    items: Vec<TryRes>,
+    inner: Inner,
+}
+
+fn outer(log: &Log) -> Outer {
+    let mut stack = DisposeStack::default();
+    stack.push(Res(Rc::clone(log), "stack"));
+
+    Outer {
+        inner: Inner {
+            res: Res(Rc::clone(log), "inner"),
+            with: WithRes(Rc::clone(log), "inner with"),
+        },
+        items: vec![Res(Rc::clone(log), "item")],
+        with_items: vec![WithRes(Rc::clone(log), "with item")],
+        stack,
+        tuple: (&(), WithRes(Rc::clone(log), "tuple")),
+    }
+}
+
+fn try_outer(log: &Log) -> TryOuter {
+    TryOuter {
+        res: TryRes(Rc::clone(log), "res"),
+        with: TryRes(Rc::clone(log), "with"),
+        items: vec![TryRes(Rc::clone(log), "item")],
+        inner: Inner {
+            res: Res(Rc::clone(log), "inner"),
+            with: WithRes(Rc::clone(log), "inner with"),
+        },
+    }
+}
+
+#[track_caller]
+fn assert_reasons(log: &Log, labels: &[&str], reason: DisposeReason) {
+    let expected: Vec<_> = labels.iter().map(|&l| (l, reason)).collect();
+
+    assert_eq!(log.take(), expected);
+}
+
+const OUTER: &[&str] = &["inner", "inner with", "item", "with item", "stack", "tuple"];
+
+const TRY_OUTER: &[&str] = &["res", "with", "item", "inner", "inner with"];
+
+#[test]
+fn nested_unwind() {
+    let log = Log::default();
+
+    catch_unwind(AssertUnwindSafe(|| {
+        let _outer = Disposable::new(outer(&log));
+        panic!("oh no");
+    }))
+    .unwrap_err();
+    assert_reasons(&log, OUTER, DisposeReason::Unwind);
+
+    catch_unwind(AssertUnwindSafe(|| {
+        let _slot = Disposable::new(Slot::Full(outer(&log)));
+        panic!("oh no");
+    }))
+    .unwrap_err();
+    assert_reasons(&log, OUTER, DisposeReason::Unwind);
+
+    catch_unwind(AssertUnwindSafe(|| {
+        let _outer = Disposable::new(vec![outer(&log)]);
+        panic!("oh no");
+    }))
+    .unwrap_err();
+    assert_reasons(&log, OUTER, DisposeReason::Unwind);
+
+    catch_unwind(AssertUnwindSafe(|| {
+        let _outer = Disposable::new(try_outer(&log));
+        panic!("oh no");
+    }))
+    .unwrap_err();
+    assert_reasons(&log, TRY_OUTER, DisposeReason::Unwind);
+}
+
+#[test]
+fn nested_scope_exit() {
+    let log = Log::default();
+
+    drop(Disposable::new(outer(&log)));
+    assert_reasons(&log, OUTER, DisposeReason::ScopeExit);
+
+    drop(Disposable::new(try_outer(&log)));
+    assert_reasons(&log, TRY_OUTER, DisposeReason::ScopeExit);
+}
+
+#[test]
+fn nested_explicit() {
+    let log = Log::default();
+
+    outer(&log).dispose();
+    assert_reasons(&log, OUTER, DisposeReason::Explicit);
+
+    Disposable::try_dispose(Disposable::new(outer(&log))).unwrap();
+    assert_reasons(&log, OUTER, DisposeReason::Explicit);
+
+    try_outer(&log).try_dispose().unwrap();
+    assert_reasons(&log, TRY_OUTER, DisposeReason::Explicit);
+}
+
+#[test]
+fn erased_disposables() {
+    let log = Log::default();
+    let erased = |log: &Log| {
+        let mut stack = DisposeStack::new();
+        stack.push_disposable(Disposable::new(Res(Rc::clone(log), "pushed")));
+        stack.push_disposable(Disposable::new(TryRes(Rc::clone(log), "try pushed")));
+
+        (stack, DynDisposable::from(Disposable::new(Res(Rc::clone(log), "dyn"))))
+    };
+    const ERASED: &[&str] = &["try pushed", "pushed", "dyn"];
+
+    catch_unwind(AssertUnwindSafe(|| {
+        let _erased = erased(&log);
+        panic!("oh no");
+    }))
+    .unwrap_err();
+    assert_reasons(&log, ERASED, DisposeReason::Unwind);
+
+    drop(erased(&log));
+    assert_reasons(&log, ERASED, DisposeReason::ScopeExit);
+
+    let (stack, dyn_disposable) = erased(&log);
+    Disposable::try_dispose(stack).unwrap();
+    unsafe { DynDisposable::leak(dyn_disposable) }.dispose();
+    assert_reasons(&log, ERASED, DisposeReason::Explicit);
+}
@@ -0,0 +1,19 @@
+use dispose::prelude::*;
+
+#[derive(Dispose)]
+struct Chain {
+    #[dispose(after = c)]
+    a: fn(),
+    #[dispose(after = a)]
+    b: fn(),
+    #[dispose(after = b)]
+    c: fn(),
+}
+
+#[derive(Dispose)]
+struct Itself {
+    #[dispose(after = a)]
+    a: fn(),
+}
+
+fn main() {}
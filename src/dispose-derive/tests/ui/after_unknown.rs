@@ -0,0 +1,13 @@
+use dispose::prelude::*;
+
+#[derive(Dispose)]
+struct Pair {
+    #[dispose(after = secnod)]
+    first: fn(),
+    second: fn(),
+}
+
+#[derive(Dispose)]
+struct Tuple(#[dispose(after = 2)] fn(), fn());
+
+fn main() {}
@@ -0,0 +1,16 @@
+use dispose::{AsyncDispose, AsyncDisposeWith};
+
+struct Device;
+struct Buffer;
+
+impl AsyncDisposeWith<&Device> for Buffer {
+    async fn dispose_with(self, _: &Device) {}
+}
+
+#[derive(AsyncDispose)]
+struct Mesh {
+    #[dispose(with_context = Device)]
+    vertices: Buffer,
+}
+
+fn main() {}
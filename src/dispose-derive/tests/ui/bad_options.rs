@@ -0,0 +1,33 @@
+use dispose::prelude::*;
+
+#[derive(Dispose)]
+#[dispose(rev)]
+struct Container {
+    a: fn(),
+}
+
+#[derive(Dispose)]
+#[dispose(default = concurrent)]
+struct Default {
+    a: fn(),
+}
+
+#[derive(Dispose)]
+struct Field {
+    #[dispose(ignore, iter)]
+    a: fn(),
+}
+
+#[derive(Dispose)]
+struct Concurrent {
+    #[dispose(concurrent)]
+    a: fn(),
+}
+
+#[derive(Dispose)]
+#[dispose(bound = "T Dispose")]
+struct Bound<T> {
+    a: T,
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use dispose::{prelude::*, DisposeWith};
+
+struct Res;
+
+impl<W> DisposeWith<W> for Res {
+    fn dispose_with(self, _: W) {}
+}
+
+#[derive(Dispose)]
+struct Mutual {
+    #[dispose(with = &self.b)]
+    a: Res,
+    #[dispose(with = &self.a)]
+    b: Res,
+}
+
+#[derive(Dispose)]
+struct Itself {
+    #[dispose(with = .a)]
+    a: Res,
+}
+
+fn main() {}
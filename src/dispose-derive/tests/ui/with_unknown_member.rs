@@ -0,0 +1,18 @@
+use dispose::{prelude::*, DisposeWith};
+
+struct Device;
+struct Buffer;
+
+impl DisposeWith<&Device> for Buffer {
+    fn dispose_with(self, _: &Device) {}
+}
+
+#[derive(Dispose)]
+struct Mesh<'a> {
+    #[dispose(ignore)]
+    device: &'a Device,
+    #[dispose(with = .devcie)]
+    vertices: Buffer,
+}
+
+fn main() {}
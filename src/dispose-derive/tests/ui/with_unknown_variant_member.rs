@@ -0,0 +1,19 @@
+use dispose::{prelude::*, DisposeWith};
+
+struct Device;
+struct Buffer;
+
+impl DisposeWith<&Device> for Buffer {
+    fn dispose_with(self, _: &Device) {}
+}
+
+#[derive(Dispose)]
+enum Resource {
+    Gpu {
+        dev: Device,
+        #[dispose(with = &self.queue)]
+        buf: Buffer,
+    },
+}
+
+fn main() {}
@@ -4,7 +4,7 @@ use syn::{
     parse::{Parse, ParseStream, Result as ParseResult},
     parse_quote,
     spanned::Spanned,
-    Expr, ExprPath, Ident, Item, Member, Token,
+    Expr, ExprLit, ExprPath, Ident, Index, Item, Lit, Member, Token,
 };
 
 #[derive(Debug, Clone)]
@@ -13,18 +13,22 @@ pub enum WithVal {
     SelfDot(Token![.], Expr),
 }
 
-struct ExpandSelf<F: Fn(Span, Member) -> Ident>(F);
+struct ExpandSelf<F: Fn(Span, Member) -> Ident> {
+    field_name: F,
+    used: Vec<Member>,
+}
 
 impl<F: Fn(Span, Member) -> Ident> Fold for ExpandSelf<F> {
     fn fold_expr(&mut self, expr: Expr) -> Expr {
         match expr {
             Expr::Field(f) if matches!(&*f.base, Expr::Path(p) if p.path.is_ident("self")) => {
                 let span = f.span();
+                self.used.push(f.member.clone());
 
                 Expr::Path(ExprPath {
                     attrs: f.attrs,
                     qself: None,
-                    path: (self.0)(span, f.member).into(),
+                    path: (self.field_name)(span, f.member).into(),
                 })
             },
             e => syn::fold::fold_expr(self, e),
@@ -37,12 +41,52 @@ impl<F: Fn(Span, Member) -> Ident> Fold for ExpandSelf<F> {
 }
 
 impl WithVal {
-    // TODO: this may produce confusing errors if a requested member doesn't exist
-    pub fn expand(self, field_name: impl Fn(Span, Member) -> Ident) -> Expr {
-        ExpandSelf(field_name).fold_expr(match self {
+    /// Replace any member accesses on `self` with the variables produced by
+    /// `field_name`, returning the resulting expression and the members that
+    /// were accessed.  The caller is responsible for checking that the members
+    /// exist.
+    pub fn expand(self, field_name: impl Fn(Span, Member) -> Ident) -> (Expr, Vec<Member>) {
+        let mut fold = ExpandSelf {
+            field_name,
+            used: vec![],
+        };
+
+        let expr = fold.fold_expr(match self {
             Self::Expr(e) => e,
             Self::SelfDot(d, e) => parse_quote! { self #d #e },
-        })
+        });
+
+        (expr, fold.used)
+    }
+
+    /// If this value is a single field of `self`, or a shared reference to
+    /// one, returns that field and whether it is borrowed.
+    pub fn self_member(&self) -> Option<(Member, bool)> {
+        let (expr, borrowed) = match self {
+            Self::SelfDot(_, Expr::Path(p)) => {
+                return p.path.get_ident().map(|i| (Member::Named(i.clone()), false));
+            },
+            Self::SelfDot(_, Expr::Lit(ExprLit {
+                lit: Lit::Int(i), ..
+            })) => {
+                let index = Index {
+                    index: i.base10_parse().ok()?,
+                    span: i.span(),
+                };
+
+                return Some((Member::Unnamed(index), false));
+            },
+            Self::SelfDot(..) => return None,
+            Self::Expr(Expr::Reference(r)) if r.mutability.is_none() => (&*r.expr, true),
+            Self::Expr(e) => (e, false),
+        };
+
+        match expr {
+            Expr::Field(f) if matches!(&*f.base, Expr::Path(p) if p.path.is_ident("self")) => {
+                Some((f.member.clone(), borrowed))
+            },
+            _ => None,
+        }
     }
 }
 
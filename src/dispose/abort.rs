@@ -1,8 +1,176 @@
 #![allow(clippy::module_name_repetitions)]
 
-use std::process::abort;
+use core::{
+    any::Any,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
-use crate::{Disposable, Dispose};
+use crate::{hook::Hook, Disposable, Dispose};
+
+static ABORT_HANDLER: Hook<fn() -> !> = unsafe { Hook::new() };
+
+/// Register a function to be called in place of `std::process::abort` when an
+/// [`AbortCanary`] is dropped, replacing any previously-registered handler.
+///
+/// This is mainly useful in `no_std` environments, where
+/// `std::process::abort` is not available.  Without the `std` feature, if no
+/// handler is registered, dropping an `AbortCanary` panics instead; for
+/// targets built with `panic = "abort"` this is sufficient, but any other
+/// target should register a handler.
+///
+/// # Examples
+///
+/// ```
+/// fn halt() -> ! {
+///     // Write to a hardware register, spin forever, etc.
+/// #   std::process::abort()
+/// }
+///
+/// dispose::set_abort_handler(halt);
+/// ```
+///
+/// [`AbortCanary`]: ./struct.AbortCanary.html
+pub fn set_abort_handler(handler: fn() -> !) { ABORT_HANDLER.set(handler); }
+
+/// Unregister the current abort handler, returning it if one was registered.
+pub fn take_abort_handler() -> Option<fn() -> !> { ABORT_HANDLER.take() }
+
+/// Abort the process, using the registered abort handler if there is one.
+pub(crate) fn abort() -> ! {
+    if let Some(handler) = ABORT_HANDLER.get() {
+        handler();
+    }
+
+    #[cfg(feature = "std")]
+    std::process::abort();
+    #[cfg(not(feature = "std"))]
+    panic!("Abort requested, but no abort handler was registered");
+}
+
+/// Information about why the process is being aborted by an [`AbortCanary`]
+/// or [`abort_on_panic`].
+///
+/// This is passed to the hook registered with [`set_abort_hook`].
+///
+/// [`AbortCanary`]: ./struct.AbortCanary.html
+/// [`abort_on_panic`]: ./fn.abort_on_panic.html
+/// [`set_abort_hook`]: ./fn.set_abort_hook.html
+#[derive(Debug, Clone, Copy)]
+pub struct AbortInfo<'a> {
+    label: Option<&'static str>,
+    payload: Option<&'a (dyn Any + Send)>,
+    thread: Option<&'a str>,
+}
+
+impl<'a> AbortInfo<'a> {
+    /// The label of the canary or critical section that failed, if it has one.
+    #[must_use]
+    pub fn label(&self) -> Option<&'static str> { self.label }
+
+    /// The payload of the panic that caused the abort, if it is known.
+    ///
+    /// The payload is only available from [`abort_on_panic`] and
+    /// [`abort_on_panic_with`] with the `std` feature enabled; a bare
+    /// [`AbortCanary`] cannot observe the panic that dropped it.
+    ///
+    /// [`abort_on_panic`]: ./fn.abort_on_panic.html
+    /// [`abort_on_panic_with`]: ./fn.abort_on_panic_with.html
+    /// [`AbortCanary`]: ./struct.AbortCanary.html
+    #[must_use]
+    pub fn payload(&self) -> Option<&'a (dyn Any + Send)> { self.payload }
+
+    /// The panic message, if the payload is known and is a string.
+    #[must_use]
+    pub fn message(&self) -> Option<&'a str> {
+        let payload = self.payload?;
+
+        #[cfg(feature = "alloc")]
+        if let Some(s) = payload.downcast_ref::<alloc::string::String>() {
+            return Some(s);
+        }
+
+        payload.downcast_ref::<&str>().copied()
+    }
+
+    /// The name of the thread that is aborting, if it has one.  Without the
+    /// `std` feature, this is always `None`.
+    #[must_use]
+    pub fn thread_name(&self) -> Option<&'a str> { self.thread }
+}
+
+impl fmt::Display for AbortInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("aborting")?;
+
+        if let Some(label) = self.label {
+            write!(f, " in `{label}`")?;
+        }
+
+        if let Some(thread) = self.thread {
+            write!(f, " on thread `{thread}`")?;
+        }
+
+        match (self.message(), self.payload) {
+            (Some(msg), _) => write!(f, ": {msg}"),
+            (None, Some(_)) => f.write_str(": <non-string panic payload>"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+static ABORT_HOOK: Hook<fn(&AbortInfo)> = unsafe { Hook::new() };
+
+/// Register a hook to be called just before an [`AbortCanary`] or
+/// [`abort_on_panic`] aborts the process, replacing any previously-registered
+/// hook.
+///
+/// This is useful for flushing logs or writing a crash report.  The hook runs
+/// after the abort message has been printed to standard error, and before the
+/// handler registered with [`set_abort_handler`] (if any).
+///
+/// # Examples
+///
+/// ```
+/// dispose::set_abort_hook(|info| {
+///     // Flush logs, write a crash file, etc.
+///     let _ = info.label();
+/// });
+/// ```
+///
+/// [`AbortCanary`]: ./struct.AbortCanary.html
+/// [`abort_on_panic`]: ./fn.abort_on_panic.html
+/// [`set_abort_handler`]: ./fn.set_abort_handler.html
+pub fn set_abort_hook(hook: fn(&AbortInfo)) { ABORT_HOOK.set(hook); }
+
+/// Unregister the current pre-abort hook, returning it if one was registered.
+pub fn take_abort_hook() -> Option<fn(&AbortInfo)> { ABORT_HOOK.take() }
+
+/// Report `info` to standard error and the pre-abort hook, then abort.
+fn abort_with(label: Option<&'static str>, payload: Option<&(dyn Any + Send)>) -> ! {
+    #[cfg(feature = "std")]
+    let thread = std::thread::current();
+
+    let info = AbortInfo {
+        label,
+        payload,
+        #[cfg(feature = "std")]
+        thread: thread.name(),
+        #[cfg(not(feature = "std"))]
+        thread: None,
+    };
+
+    #[cfg(feature = "std")]
+    eprintln!("{info}");
+
+    if let Some(hook) = ABORT_HOOK.get() {
+        hook(&info);
+    }
+
+    abort()
+}
 
 /// Abort the process if this value is dropped.
 ///
@@ -10,10 +178,15 @@ use crate::{Disposable, Dispose};
 /// wrong in an application.  It is the heart of [`abort_on_panic`], and for
 /// most cases does not need to be used directly.
 ///
-/// [`abort_on_panic`]: ./function.abort_on_panic.html
+/// Before aborting, a message containing the canary's label (if it has one)
+/// and the name of the current thread is printed to standard error, and the
+/// hook registered with [`set_abort_hook`] is called.
+///
+/// [`abort_on_panic`]: ./fn.abort_on_panic.html
+/// [`set_abort_hook`]: ./fn.set_abort_hook.html
 #[derive(Debug)]
 #[allow(missing_copy_implementations)] // Construction is trivial, copying would be dangerous
-pub struct AbortCanary(());
+pub struct AbortCanary(Option<&'static str>);
 
 impl AbortCanary {
     /// Construct a new canary.
@@ -24,21 +197,39 @@ impl AbortCanary {
     ///
     /// [`release`]: ./struct.AbortCanary.html#method.release
     #[must_use = "Dropping this value immediately will abort the process."]
-    pub fn new() -> Disposable<Self> { Disposable::new(Self(())) }
+    pub fn new() -> Disposable<Self> { Disposable::new(Self(None)) }
+
+    /// Construct a new canary with the given label, which is reported if the
+    /// canary is dropped.
+    ///
+    /// # Panics
+    /// The value produced by this function must be passed to [`release`] in
+    /// order to avoid aborting the process.
+    ///
+    /// [`release`]: ./struct.AbortCanary.html#method.release
+    #[must_use = "Dropping this value immediately will abort the process."]
+    pub fn with_label(label: &'static str) -> Disposable<Self> {
+        Disposable::new(Self(Some(label)))
+    }
+
+    /// Returns the label of this canary, if it has one.
+    #[must_use]
+    pub fn label(&self) -> Option<&'static str> { self.0 }
 
     /// Release `canary`.  This will consume and drop it without aborting the
     /// process.
-    pub fn release(canary: Disposable<Self>) { unsafe { Disposable::leak(canary) }; }
+    pub fn release(canary: Disposable<Self>) { unsafe { Disposable::take(canary) }; }
 }
 
 impl Dispose for AbortCanary {
-    fn dispose(self) { abort(); }
+    fn dispose(self) { abort_with(self.0, None); }
 }
 
 /// Abort the process if the provided closure panics.
 ///
-/// This function internally constructs an [`AbortCanary`] and releases it after
-/// running `f`.
+/// With the `std` feature enabled, the panic is caught so that its message can
+/// be reported before aborting.  Otherwise, this function internally
+/// constructs an [`AbortCanary`] and releases it after running `f`.
 ///
 /// # Example
 /// The following panic will result in the process aborting:
@@ -49,14 +240,160 @@ impl Dispose for AbortCanary {
 /// ```
 ///
 /// [`AbortCanary`]: ./struct.AbortCanary.html
-pub fn abort_on_panic<T>(f: impl FnOnce() -> T) -> T {
-    let canary = AbortCanary::new();
+pub fn abort_on_panic<T>(f: impl FnOnce() -> T) -> T { abort_on_panic_impl(None, f) }
+
+/// Abort the process if the provided closure panics, reporting `label` as the
+/// name of the critical section that failed; similar to [`abort_on_panic`].
+///
+/// # Example
+///
+/// ```should_panic
+/// # use dispose::abort_on_panic_with;
+/// // Prints "aborting in `commit` on thread `main`: oops!" before aborting
+/// abort_on_panic_with("commit", || panic!("oops!"));
+/// ```
+///
+/// [`abort_on_panic`]: ./fn.abort_on_panic.html
+pub fn abort_on_panic_with<T>(label: &'static str, f: impl FnOnce() -> T) -> T {
+    abort_on_panic_impl(Some(label), f)
+}
 
-    let ret = f();
+fn abort_on_panic_impl<T>(label: Option<&'static str>, f: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "std")]
+    return match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(ret) => ret,
+        Err(payload) => abort_with(label, Some(&*payload)),
+    };
 
-    AbortCanary::release(canary);
+    #[cfg(not(feature = "std"))]
+    {
+        let canary = Disposable::new(AbortCanary(label));
 
-    ret
+        let ret = f();
+
+        AbortCanary::release(canary);
+
+        ret
+    }
+}
+
+/// A future that aborts the process if polling the inner future panics; the
+/// asynchronous equivalent of [`abort_on_panic`].
+///
+/// Optionally, using [`abort_on_drop`], the process can also be aborted if the
+/// future is dropped after it has first been polled but before it completes,
+/// for critical sections that must not be cancelled partway through.
+///
+/// # Panics
+/// Polling the future again after it has completed panics, without polling
+/// the inner future or aborting the process.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{block_on, AbortOnPanic};
+///
+/// let val = block_on(AbortOnPanic::with_label("commit", async { 42 }).abort_on_drop());
+/// assert_eq!(val, 42);
+/// ```
+///
+/// [`abort_on_panic`]: ./fn.abort_on_panic.html
+/// [`abort_on_drop`]: ./struct.AbortOnPanic.html#method.abort_on_drop
+#[derive(Debug)]
+pub struct AbortOnPanic<F> {
+    fut: F,
+    label: Option<&'static str>,
+    abort_on_drop: bool,
+    state: PollState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PollState {
+    Idle,
+    Started,
+    Done,
+}
+
+impl<F: Future> AbortOnPanic<F> {
+    /// Wrap the given future.
+    pub fn new(fut: F) -> Self {
+        Self {
+            fut,
+            label: None,
+            abort_on_drop: false,
+            state: PollState::Idle,
+        }
+    }
+
+    /// Wrap the given future, reporting `label` as the name of the critical
+    /// section if it fails.
+    pub fn with_label(label: &'static str, fut: F) -> Self {
+        let mut this = Self::new(fut);
+        this.label = Some(label);
+        this
+    }
+
+    /// Also abort the process if this future is dropped after it has been
+    /// polled, but before it has completed.
+    #[must_use]
+    pub fn abort_on_drop(mut self) -> Self {
+        self.abort_on_drop = true;
+        self
+    }
+}
+
+impl<F: Future> Future for AbortOnPanic<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        // SAFETY: the inner future is never moved out of self
+        let this = unsafe { self.get_unchecked_mut() };
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+        let label = this.label;
+
+        match this.state {
+            PollState::Idle => this.state = PollState::Started,
+            PollState::Started => (),
+            PollState::Done => panic!("`AbortOnPanic` polled after completion"),
+        }
+
+        let poll = abort_on_panic_impl(label, || fut.poll(cx));
+
+        if poll.is_ready() {
+            this.state = PollState::Done;
+        }
+
+        poll
+    }
+}
+
+impl<F> Drop for AbortOnPanic<F> {
+    fn drop(&mut self) {
+        if self.abort_on_drop && self.state == PollState::Started {
+            abort_with(self.label, None);
+        }
+    }
+}
+
+/// Spawn a new thread whose body aborts the process if it panics, using
+/// [`abort_on_panic`].
+///
+/// This is otherwise identical to [`std::thread::spawn`].
+///
+/// # Examples
+///
+/// ```
+/// let handle = dispose::spawn_abort_on_panic(|| 6 * 7);
+///
+/// assert_eq!(handle.join().unwrap(), 42);
+/// ```
+///
+/// [`abort_on_panic`]: ./fn.abort_on_panic.html
+#[cfg(feature = "std")]
+pub fn spawn_abort_on_panic<F: FnOnce() -> T + Send + 'static, T: Send + 'static>(
+    f: F,
+) -> std::thread::JoinHandle<T> {
+    std::thread::spawn(move || abort_on_panic(f))
 }
 
 #[cfg(test)]
@@ -71,14 +408,13 @@ mod test {
         AbortCanary::release(canary);
     }
 
-    // TODO: how to test abort???
-    //       I have confirmed that uncommenting the commented tests does result
-    //       in the test process aborting, but currently the test runner can't
-    //       deal with that properly.
-
-    // #[test]
-    // #[should_panic]
-    // fn bad_canary() { let _canary = AbortCanary::new(); }
+    #[test]
+    #[cfg(feature = "std")]
+    fn bad_canary() {
+        crate::testing::assert_aborts(|| {
+            let _canary = AbortCanary::new();
+        });
+    }
 
     #[test]
     fn safe_aop() { abort_on_panic(|| ()); }
@@ -87,8 +423,76 @@ mod test {
     #[test]
     fn sanity_check_aop() { catch_unwind(|| panic!()).ok(); }
 
-    // // This should panic
-    // #[test]
-    // #[should_panic]
-    // fn bad_aop() { catch_unwind(|| abort_on_panic(|| panic!())).ok(); }
+    #[test]
+    #[cfg(feature = "std")]
+    fn bad_aop() {
+        crate::testing::assert_aborts(|| {
+            catch_unwind(|| abort_on_panic(|| panic!())).ok();
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn labeled_abort_message() {
+        use crate::testing::{run_in_subprocess, ChildExit};
+
+        let out = run_in_subprocess(|| {
+            drop(AbortCanary::with_label("canary"));
+        });
+
+        if let Some(out) = out {
+            assert_eq!(out.exit(), ChildExit::Aborted);
+            assert!(out.stderr().contains("aborting in `canary` on thread `abort::test::"));
+        }
+
+        let out = run_in_subprocess(|| {
+            abort_on_panic_with("critical", || panic!("oh no"));
+        });
+
+        if let Some(out) = out {
+            assert_eq!(out.exit(), ChildExit::Aborted);
+            assert!(out.stderr().contains("aborting in `critical` on thread"));
+            assert!(out.stderr().contains(": oh no"));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn future_aborts() {
+        use core::{pin::pin, task::Waker};
+
+        use crate::{block_on, testing::assert_aborts};
+
+        assert_eq!(block_on(AbortOnPanic::new(async { 1 }).abort_on_drop()), 1);
+        drop(AbortOnPanic::new(core::future::pending::<()>()).abort_on_drop());
+
+        assert_aborts(|| block_on(AbortOnPanic::new(async { panic!("oh no") })));
+
+        assert_aborts(|| {
+            let mut fut = pin!(AbortOnPanic::new(core::future::pending::<()>()).abort_on_drop());
+            let _ = fut.as_mut().poll(&mut Context::from_waker(Waker::noop()));
+        });
+    }
+
+    #[test]
+    #[should_panic = "`AbortOnPanic` polled after completion"]
+    fn future_polled_after_completion() {
+        use core::{pin::pin, task::Waker};
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut fut = pin!(AbortOnPanic::new(async {}).abort_on_drop());
+
+        assert!(fut.as_mut().poll(&mut cx).is_ready());
+        let _ = fut.as_mut().poll(&mut cx);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn thread_aborts() {
+        assert_eq!(spawn_abort_on_panic(|| 1).join().unwrap(), 1);
+
+        crate::testing::assert_aborts(|| {
+            let _ = spawn_abort_on_panic(|| panic!("oh no")).join();
+        });
+    }
 }
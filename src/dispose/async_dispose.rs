@@ -0,0 +1,503 @@
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    borrow::{Borrow, BorrowMut},
+    future::Future,
+    mem::{forget, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+#[cfg(feature = "std")]
+use std::{
+    sync::Arc,
+    task::Wake,
+    thread::{self, Thread},
+};
+
+use crate::unwind::{CatchUnwind, Panics};
+
+/// An asynchronous counterpart to [`Dispose`], for values whose teardown
+/// requires awaiting something.
+///
+/// Some resources cannot be released synchronously &mdash; a socket may need
+/// to be flushed, a goodbye message may need to be sent, or a GPU fence may
+/// need to be waited on.  Types like these can implement this trait and be
+/// wrapped in an [`AsyncDisposable`] to ensure they are always disposed.
+///
+/// Implementations may use `async fn dispose(self)` directly.
+///
+/// # `Send` futures
+///
+/// The future returned by [`dispose`] is not required to be `Send`, so that
+/// resources holding non-thread-safe state (such as an `Rc`) can still be
+/// disposed asynchronously.  For a concrete type, the compiler can see
+/// whether its future is `Send`, and it can be handed to a multithreaded
+/// executor as usual.  Code that is generic over `T: AsyncDispose`, however,
+/// cannot require `T`'s future to be `Send`, and must drive it on the current
+/// thread (see [`DropSpawner`] for an example of working around this).
+///
+/// [`Dispose`]: ./trait.Dispose.html
+/// [`AsyncDisposable`]: ./struct.AsyncDisposable.html
+/// [`dispose`]: ./trait.AsyncDispose.html#tymethod.dispose
+/// [`DropSpawner`]: ./trait.DropSpawner.html
+pub trait AsyncDispose {
+    /// Consume self and deinitialize its contents.
+    fn dispose(self) -> impl Future<Output = ()>;
+}
+
+impl<F: FnOnce() -> R, R: Future<Output = ()>> AsyncDispose for F {
+    /// Run the closure and await the future it returns, consuming both.
+    async fn dispose(self) { self().await }
+}
+
+impl<W, T: AsyncDisposeWith<W>> AsyncDispose for (W, T)
+where (W, T): Sized
+{
+    /// Dispose `self.1`, passing `self.0` to `dispose_with`.
+    async fn dispose(self) { self.1.dispose_with(self.0).await }
+}
+
+/// An asynchronous counterpart to [`DisposeWith`].
+///
+/// As with [`AsyncDispose`], the returned future is not required to be
+/// `Send`.
+///
+/// [`DisposeWith`]: ./trait.DisposeWith.html
+/// [`AsyncDispose`]: ./trait.AsyncDispose.html#send-futures
+pub trait AsyncDisposeWith<W> {
+    /// Dispose self, using the provided value.
+    fn dispose_with(self, with: W) -> impl Future<Output = ()>;
+}
+
+impl<W, F: FnOnce(W) -> R, R: Future<Output = ()>> AsyncDisposeWith<W> for F {
+    /// Run the closure with the given parameter and await the future it
+    /// returns, consuming all three.
+    async fn dispose_with(self, with: W) { self(with).await }
+}
+
+/// A helper trait for iterators with items implementing [`AsyncDispose`].
+///
+/// This is the asynchronous equivalent of [`DisposeIterator`].  Items are
+/// disposed one at a time, in iteration order, and as with `DisposeIterator`,
+/// all items are disposed even if one of them panics.
+///
+/// [`AsyncDispose`]: ./trait.AsyncDispose.html
+/// [`DisposeIterator`]: ./trait.DisposeIterator.html
+pub trait AsyncDisposeIterator {
+    /// Dispose all items in the iterator, consuming it.
+    fn dispose_iter(self) -> impl Future<Output = ()>;
+}
+
+impl<I: IntoIterator> AsyncDisposeIterator for I
+where I::Item: AsyncDispose
+{
+    async fn dispose_iter(self) {
+        let mut panics = Panics::new();
+
+        for el in self {
+            panics.record(CatchUnwind::new(el.dispose()).await);
+        }
+
+        panics.resume();
+    }
+}
+
+/// A helper trait for iterators with items implementing [`AsyncDisposeWith`].
+///
+/// This is the asynchronous equivalent of [`DisposeIteratorWith`].
+///
+/// [`AsyncDisposeWith`]: ./trait.AsyncDisposeWith.html
+/// [`DisposeIteratorWith`]: ./trait.DisposeIteratorWith.html
+pub trait AsyncDisposeIteratorWith<W> {
+    /// Dispose all items in the iterator using the provided value, consuming
+    /// both.
+    fn dispose_iter_with(self, with: W) -> impl Future<Output = ()>;
+}
+
+impl<W: Copy, I: IntoIterator> AsyncDisposeIteratorWith<W> for I
+where I::Item: AsyncDisposeWith<W>
+{
+    /// Dispose all items in the iterator, using copies of the provided value.
+    async fn dispose_iter_with(self, with: W) {
+        let mut panics = Panics::new();
+
+        for el in self {
+            panics.record(CatchUnwind::new(el.dispose_with(with)).await);
+        }
+
+        panics.resume();
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> AsyncDispose for Vec<T>
+where Vec<T>: AsyncDisposeIterator
+{
+    async fn dispose(self) { self.dispose_iter().await }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> AsyncDispose for Box<[T]>
+where Vec<T>: AsyncDisposeIterator
+{
+    async fn dispose(self) { self.into_vec().dispose_iter().await }
+}
+
+impl<'a, T> AsyncDispose for &'a [T]
+where &'a [T]: AsyncDisposeIterator
+{
+    async fn dispose(self) { self.dispose_iter().await }
+}
+
+#[cfg(feature = "alloc")]
+impl<W, T> AsyncDisposeWith<W> for Vec<T>
+where Vec<T>: AsyncDisposeIteratorWith<W>
+{
+    async fn dispose_with(self, with: W) { self.dispose_iter_with(with).await }
+}
+
+#[cfg(feature = "alloc")]
+impl<W, T> AsyncDisposeWith<W> for Box<[T]>
+where Vec<T>: AsyncDisposeIteratorWith<W>
+{
+    async fn dispose_with(self, with: W) { self.into_vec().dispose_iter_with(with).await }
+}
+
+impl<'a, W, T> AsyncDisposeWith<W> for &'a [T]
+where &'a [T]: AsyncDisposeIteratorWith<W>
+{
+    async fn dispose_with(self, with: W) { self.dispose_iter_with(with).await }
+}
+
+/// A strategy for disposing an [`AsyncDispose`] value from a synchronous
+/// context.
+///
+/// When an [`AsyncDisposable`] is dropped without first being disposed with
+/// [`AsyncDisposable::dispose`], there is no way to `.await` its contents.
+/// Instead, the value is passed to a `DropSpawner`, which is responsible for
+/// driving its disposal to completion, for instance by spawning a task on an
+/// async runtime.
+///
+/// The future returned by [`AsyncDispose::dispose`] is not required to be
+/// `Send` (see [here][send]), so a spawner implemented for every
+/// `T: AsyncDispose` cannot move it to another thread, and must drive it on
+/// the current one, as [`BlockOn`] and the local queue below do.  Spawners that
+/// hand values off to a multithreaded executor should instead be implemented
+/// for the concrete types they accept, whose futures the compiler knows to be
+/// `Send`.
+///
+/// # Examples
+///
+/// ```
+/// use std::{cell::RefCell, future::Future, pin::Pin};
+///
+/// use dispose::{AsyncDispose, AsyncDisposable, DropSpawner};
+///
+/// // A stand-in for a real async executor
+/// #[derive(Default)]
+/// struct TaskQueue(RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>>);
+///
+/// impl<'a, T: AsyncDispose + 'static> DropSpawner<T> for &'a TaskQueue {
+///     fn spawn_dispose(&self, val: T) {
+///         self.0.borrow_mut().push(Box::pin(val.dispose()));
+///     }
+/// }
+///
+/// let queue = TaskQueue::default();
+///
+/// {
+///     let goodbye = || async { println!("goodbye!") };
+///     let _socket = AsyncDisposable::with_spawner(goodbye, &queue);
+/// }
+///
+/// assert_eq!(queue.0.borrow().len(), 1);
+/// ```
+///
+/// Disposing a specific type on another thread:
+///
+/// ```
+/// use std::thread;
+///
+/// use dispose::{block_on, AsyncDispose, AsyncDisposable, DropSpawner};
+///
+/// struct Socket(&'static str);
+///
+/// impl AsyncDispose for Socket {
+///     async fn dispose(self) { println!("closing {}", self.0); }
+/// }
+///
+/// // A stand-in for a multithreaded executor's spawn function
+/// struct ThreadSpawner;
+///
+/// impl DropSpawner<Socket> for ThreadSpawner {
+///     fn spawn_dispose(&self, val: Socket) {
+///         let fut = val.dispose(); // Known to be Send, since Socket is concrete
+///         thread::spawn(move || block_on(fut)).join().unwrap();
+///     }
+/// }
+///
+/// drop(AsyncDisposable::with_spawner(Socket("control"), ThreadSpawner));
+/// ```
+///
+/// [`AsyncDispose`]: ./trait.AsyncDispose.html
+/// [`AsyncDispose::dispose`]: ./trait.AsyncDispose.html#tymethod.dispose
+/// [send]: ./trait.AsyncDispose.html#send-futures
+/// [`BlockOn`]: ./struct.BlockOn.html
+/// [`AsyncDisposable`]: ./struct.AsyncDisposable.html
+/// [`AsyncDisposable::dispose`]: ./struct.AsyncDisposable.html#method.dispose
+pub trait DropSpawner<T: AsyncDispose> {
+    /// Arrange for `val` to be disposed.
+    fn spawn_dispose(&self, val: T);
+}
+
+/// A [`DropSpawner`] that blocks the current thread until the value has been
+/// disposed, using [`block_on`].
+///
+/// This is the default spawner used by [`AsyncDisposable`].  Note that
+/// blocking inside an async runtime can stall or deadlock it, so values that
+/// may be dropped inside of one should either be disposed explicitly or use a
+/// spawner that hands them off to the runtime.
+///
+/// [`DropSpawner`]: ./trait.DropSpawner.html
+/// [`block_on`]: ./fn.block_on.html
+/// [`AsyncDisposable`]: ./struct.AsyncDisposable.html
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlockOn;
+
+impl<T: AsyncDispose> DropSpawner<T> for BlockOn {
+    fn spawn_dispose(&self, val: T) { block_on(val.dispose()) }
+}
+
+#[cfg(feature = "std")]
+struct ThreadWaker(Thread);
+
+#[cfg(feature = "std")]
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) { self.0.unpark(); }
+
+    fn wake_by_ref(self: &Arc<Self>) { self.0.unpark(); }
+}
+
+/// Run a future to completion on the current thread, blocking until it
+/// completes.
+///
+/// This is a minimal executor intended for disposing values from synchronous
+/// code, and is what [`BlockOn`] uses internally.  Without the `std` feature,
+/// the current thread cannot be parked, so this function instead polls the
+/// future in a busy loop.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::block_on;
+///
+/// assert_eq!(block_on(async { 6 * 7 }), 42);
+/// ```
+///
+/// [`BlockOn`]: ./struct.BlockOn.html
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    #[cfg(feature = "std")]
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    #[cfg(not(feature = "std"))]
+    let waker = Waker::noop().clone();
+
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = pin!(fut);
+
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(r) => break r,
+            #[cfg(feature = "std")]
+            Poll::Pending => thread::park(),
+            #[cfg(not(feature = "std"))]
+            Poll::Pending => core::hint::spin_loop(),
+        }
+    }
+}
+
+/// Wrapper for values implementing [`AsyncDispose`] that provides a `Drop`
+/// implementation.
+///
+/// This is the asynchronous equivalent of [`Disposable`].  The contained value
+/// should be disposed explicitly by awaiting [`AsyncDisposable::dispose`], but
+/// if the wrapper is dropped instead, its contents are passed to the
+/// [`DropSpawner`] `S` so that they are never silently leaked.  By default,
+/// this blocks the current thread using [`BlockOn`].
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{block_on, AsyncDispose, AsyncDisposable};
+///
+/// struct Socket;
+///
+/// impl AsyncDispose for Socket {
+///     async fn dispose(self) { println!("sending goodbye frame..."); }
+/// }
+///
+/// block_on(async {
+///     let socket = AsyncDisposable::new(Socket);
+///
+///     AsyncDisposable::dispose(socket).await; // prints "sending goodbye frame..."
+/// });
+///
+/// {
+///     let _socket = AsyncDisposable::new(Socket);
+/// } // also prints "sending goodbye frame...", blocking until it finishes
+/// ```
+///
+/// [`AsyncDispose`]: ./trait.AsyncDispose.html
+/// [`Disposable`]: ./struct.Disposable.html
+/// [`AsyncDisposable::dispose`]: ./struct.AsyncDisposable.html#method.dispose
+/// [`DropSpawner`]: ./trait.DropSpawner.html
+/// [`BlockOn`]: ./struct.BlockOn.html
+#[derive(Debug)]
+pub struct AsyncDisposable<T: AsyncDispose, S: DropSpawner<T> = BlockOn> {
+    val: ManuallyDrop<T>,
+    spawner: ManuallyDrop<S>,
+}
+
+impl<T: AsyncDispose> AsyncDisposable<T> {
+    /// Construct a new `AsyncDisposable` instance, wrapping around `val`.
+    pub fn new(val: T) -> Self { Self::with_spawner(val, BlockOn) }
+}
+
+impl<T: AsyncDispose, S: DropSpawner<T>> AsyncDisposable<T, S> {
+    /// Construct a new `AsyncDisposable` instance, wrapping around `val` and
+    /// using `spawner` to dispose it if the wrapper is dropped.
+    pub fn with_spawner(val: T, spawner: S) -> Self {
+        Self {
+            val: ManuallyDrop::new(val),
+            spawner: ManuallyDrop::new(spawner),
+        }
+    }
+
+    /// Consume the wrapper, producing the contained value.
+    ///
+    /// # Safety
+    ///
+    /// See [`Disposable::leak`] &mdash; the same considerations apply here.
+    ///
+    /// [`Disposable::leak`]: ./struct.Disposable.html#method.leak
+    pub unsafe fn leak(mut this: Self) -> T {
+        let inner = ManuallyDrop::take(&mut this.val);
+        ManuallyDrop::drop(&mut this.spawner);
+        forget(this);
+        inner
+    }
+
+    /// Consume the wrapper, asynchronously disposing the contained value.
+    pub async fn dispose(this: Self) { unsafe { Self::leak(this) }.dispose().await }
+}
+
+impl<T: AsyncDispose> From<T> for AsyncDisposable<T> {
+    fn from(val: T) -> Self { Self::new(val) }
+}
+
+impl<T: AsyncDispose, S: DropSpawner<T>> Drop for AsyncDisposable<T, S> {
+    fn drop(&mut self) {
+        let (inner, spawner) =
+            unsafe { (ManuallyDrop::take(&mut self.val), ManuallyDrop::take(&mut self.spawner)) };
+
+        spawner.spawn_dispose(inner);
+    }
+}
+
+impl<T: AsyncDispose, S: DropSpawner<T>> AsRef<T> for AsyncDisposable<T, S> {
+    fn as_ref(&self) -> &T { &self.val }
+}
+
+impl<T: AsyncDispose, S: DropSpawner<T>> AsMut<T> for AsyncDisposable<T, S> {
+    fn as_mut(&mut self) -> &mut T { &mut self.val }
+}
+
+impl<T: AsyncDispose, S: DropSpawner<T>> Borrow<T> for AsyncDisposable<T, S> {
+    fn borrow(&self) -> &T { self.as_ref() }
+}
+
+impl<T: AsyncDispose, S: DropSpawner<T>> BorrowMut<T> for AsyncDisposable<T, S> {
+    fn borrow_mut(&mut self) -> &mut T { self.as_mut() }
+}
+
+impl<T: AsyncDispose, S: DropSpawner<T>> Deref for AsyncDisposable<T, S> {
+    type Target = T;
+
+    fn deref(&self) -> &T { self.as_ref() }
+}
+
+impl<T: AsyncDispose, S: DropSpawner<T>> DerefMut for AsyncDisposable<T, S> {
+    fn deref_mut(&mut self) -> &mut T { self.as_mut() }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::RefCell,
+        future::Future,
+        pin::Pin,
+        rc::Rc,
+        task::{Context, Poll},
+    };
+
+    use super::{block_on, AsyncDispose, AsyncDisposable};
+
+    struct Yield(bool);
+
+    impl Future for Yield {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    struct Res(u32, Rc<RefCell<Vec<u32>>>);
+
+    impl AsyncDispose for Res {
+        async fn dispose(self) {
+            Yield(false).await;
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    #[test]
+    fn explicit_dispose() {
+        let log = Rc::default();
+        let res = AsyncDisposable::new(Res(1, Rc::clone(&log)));
+
+        block_on(AsyncDisposable::dispose(res));
+        assert_eq!(*log.borrow(), [1]);
+    }
+
+    #[test]
+    fn drop_blocks() {
+        let log = Rc::default();
+
+        {
+            let _res = AsyncDisposable::new(Res(1, Rc::clone(&log)));
+        }
+
+        assert_eq!(*log.borrow(), [1]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn dispose_vec() {
+        let log = Rc::default();
+        let res = AsyncDisposable::new(vec![
+            Res(1, Rc::clone(&log)),
+            Res(2, Rc::clone(&log)),
+            Res(3, Rc::clone(&log)),
+        ]);
+
+        drop(res);
+        assert_eq!(*log.borrow(), [1, 2, 3]);
+    }
+}
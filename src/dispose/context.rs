@@ -0,0 +1,260 @@
+use std::{
+    any::{type_name, TypeId},
+    boxed::Box,
+    cell::RefCell,
+    error::Error,
+    fmt, ptr,
+    vec::Vec,
+};
+
+use crate::defer;
+
+type BoxedError = Box<dyn fmt::Debug + Send + Sync>;
+
+thread_local! {
+    static CONTEXTS: RefCell<Vec<(TypeId, *const ())>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The error produced when no dispose context of the requested type has been
+/// set on the current thread.
+///
+/// See [`with_dispose_context`] for more info.  Types deriving `TryDispose`
+/// record this error for any `#[dispose(with_context = ...)]` field disposed
+/// without a context.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{prelude::*, with_dispose_context, Disposable};
+///
+/// struct Device;
+/// struct Buffer;
+///
+/// impl DisposeWith<&Device> for Buffer {
+///     fn dispose_with(self, _: &Device) {}
+/// }
+///
+/// #[derive(TryDispose)]
+/// struct Mesh {
+///     #[dispose(with_context = Device)]
+///     vertices: Buffer,
+/// }
+///
+/// let mesh = Disposable::new(Mesh { vertices: Buffer });
+/// let err = Disposable::try_dispose(mesh).unwrap_err();
+/// let failed: Vec<_> = err.iter().map(|(name, _)| name).collect();
+/// assert_eq!(failed, ["vertices"]);
+///
+/// let mesh = Disposable::new(Mesh { vertices: Buffer });
+/// with_dispose_context(&Device, || Disposable::try_dispose(mesh)).unwrap();
+/// ```
+///
+/// [`with_dispose_context`]: ./fn.with_dispose_context.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingContext {
+    type_name: &'static str,
+}
+
+impl MissingContext {
+    /// The name of the type of the missing context.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str { self.type_name }
+}
+
+impl fmt::Display for MissingContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "no dispose context of type `{}` is set; use `with_dispose_context` to provide one",
+            self.type_name
+        )
+    }
+}
+
+impl Error for MissingContext {}
+
+/// Run `f` with `ctx` set as the dispose context of type `C` on the current
+/// thread.
+///
+/// While `f` runs, [`dispose_context`] can be used to retrieve `ctx` without
+/// threading a reference to it through every value that needs it at teardown.
+/// The derive macros use this for fields marked with
+/// `#[dispose(with_context = C)]`, which are disposed with a `&C` retrieved
+/// from the current context.
+///
+/// Contexts may be nested; the innermost context of a given type takes
+/// precedence, and contexts of different types do not interfere with one
+/// another.  The context is removed when `f` returns or panics.
+///
+/// Since contexts are looked up by type, `C` must be `'static` (although the
+/// reference to `ctx` need not be).  This rules out context types with
+/// lifetime parameters, such as a `Device<'a>` borrowing from some instance;
+/// values needing such a context should be disposed with
+/// `#[dispose(with = ...)]` instead.
+///
+/// If the thread-local storage holding the contexts has already been
+/// destroyed, as can happen when this function is called from the destructor
+/// of another thread-local value, `f` is run without the context, and
+/// [`dispose_context`] reports it as missing.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{prelude::*, with_dispose_context, Disposable};
+///
+/// struct Device(&'static str);
+/// struct Buffer(u32);
+///
+/// impl DisposeWith<&Device> for Buffer {
+///     fn dispose_with(self, dev: &Device) { println!("freeing buffer {} on {}", self.0, dev.0); }
+/// }
+///
+/// #[derive(Dispose)]
+/// struct Mesh {
+///     #[dispose(with_context = Device)]
+///     vertices: Buffer,
+///     #[dispose(with_context = Device)]
+///     indices: Buffer,
+/// }
+///
+/// let dev = Device("gpu0");
+///
+/// with_dispose_context(&dev, || {
+///     let _mesh = Disposable::new(Mesh {
+///         vertices: Buffer(1),
+///         indices: Buffer(2),
+///     });
+/// }); // Prints "freeing buffer 1 on gpu0" and "freeing buffer 2 on gpu0"
+/// ```
+///
+/// [`dispose_context`]: ./fn.dispose_context.html
+pub fn with_dispose_context<C: 'static, R>(ctx: &C, f: impl FnOnce() -> R) -> R {
+    let entry = (TypeId::of::<C>(), ptr::from_ref(ctx).cast());
+    let pushed = CONTEXTS.try_with(|c| c.borrow_mut().push(entry)).is_ok();
+    let _pop = defer(|| {
+        if pushed {
+            CONTEXTS.try_with(|c| c.borrow_mut().pop()).ok();
+        }
+    });
+
+    f()
+}
+
+/// Call `f` with the innermost dispose context of type `C` set on the current
+/// thread.
+///
+/// See [`with_dispose_context`] for more info.
+///
+/// # Errors
+/// This function returns an error, without calling `f`, if no context of type
+/// `C` is set, or if the current thread's contexts have already been destroyed.
+///
+/// [`with_dispose_context`]: ./fn.with_dispose_context.html
+pub fn dispose_context<C: 'static, R>(f: impl FnOnce(&C) -> R) -> Result<R, MissingContext> {
+    let id = TypeId::of::<C>();
+    let ctx = CONTEXTS
+        .try_with(|c| c.borrow().iter().rev().find(|(i, _)| *i == id).map(|(_, p)| *p))
+        .ok()
+        .flatten();
+
+    match ctx {
+        // SAFETY: the pointer was created from a &C which outlives the call to
+        //         with_dispose_context that registered it, and f cannot retain
+        //         the reference past its own return
+        Some(ptr) => Ok(f(unsafe { &*ptr.cast::<C>() })),
+        None => Err(MissingContext {
+            type_name: type_name::<C>(),
+        }),
+    }
+}
+
+/// Dispose a field using the current context, panicking if it is missing.
+/// Used by the derive macros.
+///
+/// # Panics
+/// This function panics if no context of type `C` is set.
+pub fn expect_context<C: 'static, R>(f: impl FnOnce(&C) -> R) -> R {
+    dispose_context(f).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Try to dispose a field using the current context, returning an error if
+/// it is missing.  Used by the derive macros.
+///
+/// Since the type of the error produced by disposing the field may depend on
+/// the lifetime of the context reference, `f` should box it with
+/// [`box_error`].
+///
+/// # Errors
+/// This function returns an error if the context is missing or `f` fails.
+pub fn try_context<C: 'static>(
+    f: impl FnOnce(&C) -> Result<(), BoxedError>,
+) -> Result<(), BoxedError> {
+    dispose_context(f).unwrap_or_else(|e| Err(Box::new(e)))
+}
+
+/// Box the error produced by disposing a field with [`try_context`].  Used by
+/// the derive macros.
+///
+/// # Errors
+/// This function returns an error if `res` is an error.
+pub fn box_error<E: fmt::Debug + Send + Sync + 'static>(
+    res: Result<(), E>,
+) -> Result<(), BoxedError> {
+    res.map_err(|e| Box::new(e) as BoxedError)
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use super::*;
+
+    #[test]
+    fn nested_contexts() {
+        assert!(dispose_context::<u32, _>(|_| ()).is_err());
+
+        with_dispose_context(&1_u32, || {
+            with_dispose_context(&"a", || {
+                with_dispose_context(&2_u32, || {
+                    assert_eq!(dispose_context(|c: &u32| *c), Ok(2));
+                });
+
+                assert_eq!(dispose_context(|c: &u32| *c), Ok(1));
+                assert_eq!(dispose_context(|c: &&str| *c), Ok("a"));
+            });
+        });
+
+        let err = dispose_context::<u32, _>(|_| ()).unwrap_err();
+        assert_eq!(err.type_name(), "u32");
+    }
+
+    #[test]
+    fn removed_on_panic() {
+        catch_unwind(AssertUnwindSafe(|| with_dispose_context(&1_u32, || panic!()))).unwrap_err();
+
+        assert!(dispose_context::<u32, _>(|_| ()).is_err());
+    }
+
+    #[test]
+    fn thread_teardown() {
+        struct Late;
+
+        impl Drop for Late {
+            fn drop(&mut self) {
+                assert!(dispose_context::<u32, _>(|_| ()).is_err());
+                with_dispose_context(&1_u32, || dispose_context(|_: &u32| ()).ok());
+            }
+        }
+
+        thread_local!(static LATE: Late = const { Late });
+
+        // Registering the destructor for LATE first means it typically runs
+        // after CONTEXTS has been destroyed
+        std::thread::spawn(|| {
+            LATE.with(|_| ());
+            with_dispose_context(&0_u32, || ());
+        })
+        .join()
+        .unwrap();
+    }
+}
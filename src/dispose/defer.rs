@@ -1,4 +1,4 @@
-use super::Disposable;
+use super::{Disposable, Dispose, DisposeReason};
 
 /// Defer an action until the end of a lexical scope.
 ///
@@ -59,3 +59,101 @@ pub fn defer<F: FnOnce()>(f: F) -> Disposable<F> { f.into() }
 /// [`defer`]: ./fn.defer.html
 /// [`FnOnce(W)`]: ./trait.DisposeWith.html#impl-DisposeWith<W>
 pub fn defer_with<W, F: FnOnce(W)>(with: W, f: F) -> Disposable<(W, F)> { (with, f).into() }
+
+/// A closure that receives the reason it is being run, as returned by
+/// [`defer_with_reason`].
+///
+/// If disposed using [`Dispose::dispose`] rather than
+/// [`Dispose::dispose_because`], the closure receives
+/// [`DisposeReason::Explicit`].
+///
+/// [`defer_with_reason`]: ./fn.defer_with_reason.html
+/// [`Dispose::dispose`]: ./trait.Dispose.html#tymethod.dispose
+/// [`Dispose::dispose_because`]: ./trait.Dispose.html#method.dispose_because
+/// [`DisposeReason::Explicit`]: ./enum.DisposeReason.html#variant.Explicit
+#[derive(Debug)]
+pub struct ReasonFn<F: FnOnce(DisposeReason)>(pub F);
+
+impl<F: FnOnce(DisposeReason)> Dispose for ReasonFn<F> {
+    fn dispose(self) { (self.0)(DisposeReason::Explicit) }
+
+    fn dispose_because(self, reason: DisposeReason) { (self.0)(reason) }
+}
+
+/// Defer an action until the end of a lexical scope, passing the reason the
+/// scope was exited; similar to [`defer`].
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{defer_with_reason, DisposeReason};
+///
+/// let _d = defer_with_reason(|reason| {
+///     if reason == DisposeReason::Unwind {
+///         println!("Something went wrong!");
+///     }
+/// });
+/// ```
+///
+/// [`defer`]: ./fn.defer.html
+pub fn defer_with_reason<F: FnOnce(DisposeReason)>(f: F) -> Disposable<ReasonFn<F>> {
+    ReasonFn(f).into()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use std::{
+        cell::RefCell,
+        panic::{catch_unwind, AssertUnwindSafe},
+        vec::Vec,
+    };
+
+    use super::*;
+    use crate::DisposeWith;
+
+    struct Logged<'a>(&'a RefCell<Vec<DisposeReason>>);
+
+    impl DisposeWith<()> for Logged<'_> {
+        fn dispose_with(self, (): ()) { self.dispose_with_because((), DisposeReason::Explicit) }
+
+        fn dispose_with_because(self, (): (), reason: DisposeReason) {
+            self.0.borrow_mut().push(reason);
+        }
+    }
+
+    #[test]
+    fn reasons() {
+        let log = RefCell::new(Vec::new());
+        let push = |r| log.borrow_mut().push(r);
+
+        drop(defer_with_reason(push));
+        Disposable::try_dispose(defer_with_reason(push)).unwrap();
+        catch_unwind(AssertUnwindSafe(|| {
+            let _d = defer_with_reason(push);
+            panic!("oh no");
+        }))
+        .unwrap_err();
+
+        assert_eq!(*log.borrow(), [
+            DisposeReason::ScopeExit,
+            DisposeReason::Explicit,
+            DisposeReason::Unwind,
+        ]);
+    }
+
+    #[test]
+    fn nested_reasons() {
+        let log = RefCell::new(Vec::new());
+        let push = |r| log.borrow_mut().push(r);
+
+        catch_unwind(AssertUnwindSafe(|| {
+            let _v = Disposable::new(vec![ReasonFn(push), ReasonFn(push)]);
+            let _b = Disposable::new(vec![ReasonFn(push)].into_boxed_slice());
+            let _t = Disposable::new(((), vec![Logged(&log)]));
+            panic!("oh no");
+        }))
+        .unwrap_err();
+
+        assert_eq!(*log.borrow(), [DisposeReason::Unwind; 4]);
+    }
+}
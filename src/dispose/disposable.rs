@@ -1,27 +1,53 @@
-use std::{
+use core::{
     borrow::{Borrow, BorrowMut},
+    fmt,
     mem::{forget, ManuallyDrop},
     ops::{Deref, DerefMut},
 };
 
-use super::Dispose;
+#[cfg(feature = "std")]
+use crate::drop_panic::guard_drop_panic;
+#[cfg(feature = "leak-check")]
+use crate::leak_check::Tracker;
+use crate::{try_dispose::report_drop_error, DisposeReason, TryDispose};
+
+/// Stand-in for the live-resource tracker when the `leak-check` feature is
+/// disabled.
+#[cfg(not(feature = "leak-check"))]
+struct Tracker;
+
+#[cfg(not(feature = "leak-check"))]
+#[allow(clippy::extra_unused_type_parameters, clippy::unused_self)]
+impl Tracker {
+    fn new<T: ?Sized>() -> Self { Self }
+
+    fn release(&self) {}
+
+    fn leak(&self) {}
+}
 
 /// Wrapper for values implementing [`Dispose`] that provides a `Drop`
 /// implementation.
 ///
 /// This struct will automatically consume its contents on drop using the
-/// provided [`Dispose`] implementation.
+/// provided [`Dispose`] implementation, passing a [`DisposeReason`] of
+/// `ScopeExit` or `Unwind` as appropriate.  Values implementing [`TryDispose`]
+/// can also be wrapped; any errors produced while dropping them are passed to
+/// the hook registered with [`set_drop_error_hook`].
 ///
 /// See [this page][examples] for example usage.
 ///
 /// [`Dispose`]: ./trait.Dispose.html
+/// [`DisposeReason`]: ./enum.DisposeReason.html
+/// [`TryDispose`]: ./trait.TryDispose.html
+/// [`set_drop_error_hook`]: ./fn.set_drop_error_hook.html
+/// [`DropPanicPolicy`]: ./enum.DropPanicPolicy.html
 /// [examples]: ./index.html#examples
-#[derive(Debug)]
-pub struct Disposable<T: Dispose>(ManuallyDrop<T>);
+pub struct Disposable<T: TryDispose>(ManuallyDrop<T>, Tracker);
 
-impl<T: Dispose> Disposable<T> {
+impl<T: TryDispose> Disposable<T> {
     /// Construct a new `Disposable` instance, wrapping around `val`.
-    pub fn new(val: T) -> Self { Self(ManuallyDrop::new(val)) }
+    pub fn new(val: T) -> Self { Self(ManuallyDrop::new(val), Tracker::new::<T>()) }
 
     /// Consume the wrapper, producing the contained value.
     ///
@@ -35,47 +61,86 @@ impl<T: Dispose> Disposable<T> {
     /// recommended that the value is held by some container which
     /// consumes it on drop at all times.  The intended use case for this
     /// function is transferring the value from one container to the other.
-    pub unsafe fn leak(mut this: Self) -> T {
+    pub unsafe fn leak(this: Self) -> T {
+        this.1.leak();
+        Self::take(this)
+    }
+
+    /// Consume the wrapper, producing the contained value without recording a
+    /// leak.  Used for values that are intentionally released.
+    pub(crate) unsafe fn take(mut this: Self) -> T {
+        this.1.release();
         let inner = ManuallyDrop::take(&mut this.0);
         forget(this);
         inner
     }
+
+    /// Consume the wrapper, disposing the contained value and returning any
+    /// error produced by [`TryDispose::try_dispose`].
+    ///
+    /// Unlike dropping the wrapper, this function does not pass errors to the
+    /// drop error hook.
+    ///
+    /// # Errors
+    /// This function returns an error if disposing the contained value failed.
+    ///
+    /// [`TryDispose::try_dispose`]: ./trait.TryDispose.html#tymethod.try_dispose
+    pub fn try_dispose(this: Self) -> Result<(), T::Error> {
+        unsafe { Self::take(this) }.try_dispose_because(DisposeReason::Explicit)
+    }
 }
 
-impl<T: Dispose> From<T> for Disposable<T> {
+impl<T: TryDispose> From<T> for Disposable<T> {
     fn from(val: T) -> Self { Self::new(val) }
 }
 
-impl<T: Dispose> Drop for Disposable<T> {
+impl<T: TryDispose> Drop for Disposable<T> {
     fn drop(&mut self) {
+        self.1.release();
         let inner = unsafe { ManuallyDrop::take(&mut self.0) };
+        let reason = DisposeReason::on_drop();
+
+        let dispose = || {
+            if let Err(e) = inner.try_dispose_because(reason) {
+                report_drop_error::<T>(&e);
+            }
+        };
+
+        #[cfg(feature = "std")]
+        guard_drop_panic::<T>(reason, dispose);
+        #[cfg(not(feature = "std"))]
+        dispose();
+    }
+}
 
-        inner.dispose();
+impl<T: TryDispose + fmt::Debug> fmt::Debug for Disposable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Disposable").field(&self.0).finish()
     }
 }
 
-impl<T: Dispose> AsRef<T> for Disposable<T> {
+impl<T: TryDispose> AsRef<T> for Disposable<T> {
     fn as_ref(&self) -> &T { &self.0 }
 }
 
-impl<T: Dispose> AsMut<T> for Disposable<T> {
+impl<T: TryDispose> AsMut<T> for Disposable<T> {
     fn as_mut(&mut self) -> &mut T { &mut self.0 }
 }
 
-impl<T: Dispose> Borrow<T> for Disposable<T> {
+impl<T: TryDispose> Borrow<T> for Disposable<T> {
     fn borrow(&self) -> &T { self.as_ref() }
 }
 
-impl<T: Dispose> BorrowMut<T> for Disposable<T> {
+impl<T: TryDispose> BorrowMut<T> for Disposable<T> {
     fn borrow_mut(&mut self) -> &mut T { self.as_mut() }
 }
 
-impl<T: Dispose> Deref for Disposable<T> {
+impl<T: TryDispose> Deref for Disposable<T> {
     type Target = T;
 
     fn deref(&self) -> &T { self.as_ref() }
 }
 
-impl<T: Dispose> DerefMut for Disposable<T> {
+impl<T: TryDispose> DerefMut for Disposable<T> {
     fn deref_mut(&mut self) -> &mut T { self.as_mut() }
 }
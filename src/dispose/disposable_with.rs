@@ -0,0 +1,165 @@
+use core::{
+    borrow::{Borrow, BorrowMut},
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{Disposable, DisposeWith};
+
+/// Wrapper for values implementing [`DisposeWith<W>`] that holds the value to
+/// dispose them with, and provides a `Drop` implementation.
+///
+/// This behaves like a [`Disposable`] wrapping a `(W, T)` tuple, but
+/// dereferences to the `T` directly.  `W` is usually a reference or another
+/// cheap `Copy` handle, such as `&'a Device`.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{DisposableWith, DisposeWith};
+///
+/// struct Device;
+/// struct Buffer(Vec<u8>);
+///
+/// impl Device {
+///     fn free(&self, buf: Vec<u8>) { println!("freeing {} bytes", buf.len()); }
+/// }
+///
+/// impl DisposeWith<&Device> for Buffer {
+///     fn dispose_with(self, dev: &Device) { dev.free(self.0); }
+/// }
+///
+/// let dev = Device;
+///
+/// {
+///     let mut buf = DisposableWith::new(&dev, Buffer(vec![]));
+///     buf.0.extend_from_slice(b"hello");
+///
+///     assert_eq!(buf.0.len(), 5);
+/// } // Prints "freeing 5 bytes"
+/// ```
+///
+/// [`DisposeWith<W>`]: ./trait.DisposeWith.html
+/// [`Disposable`]: ./struct.Disposable.html
+pub struct DisposableWith<W, T: DisposeWith<W>>(Disposable<(W, T)>);
+
+impl<W, T: DisposeWith<W>> DisposableWith<W, T> {
+    /// Construct a new `DisposableWith` instance, wrapping around `val` and
+    /// passing `with` to it when disposed.
+    pub fn new(with: W, val: T) -> Self { Self(Disposable::new((with, val))) }
+
+    /// Returns the value that will be passed to `dispose_with`.
+    pub fn with(this: &Self) -> W
+    where W: Copy {
+        this.0 .0
+    }
+
+    /// Consume the wrapper, producing the value to dispose with and the
+    /// contained value.
+    ///
+    /// # Safety
+    ///
+    /// See [`Disposable::leak`].
+    ///
+    /// [`Disposable::leak`]: ./struct.Disposable.html#method.leak
+    pub unsafe fn into_parts(this: Self) -> (W, T) { Disposable::leak(this.0) }
+
+    /// Replace the contained value with the result of passing it to `f`,
+    /// keeping the same value to dispose with.
+    ///
+    /// If `f` panics, the contained value is owned by `f` and will not be
+    /// disposed unless `f` does so itself.
+    pub fn map<U: DisposeWith<W>>(this: Self, f: impl FnOnce(T) -> U) -> DisposableWith<W, U> {
+        // SAFETY: both values move straight into the new wrapper (val by way
+        //         of f, which owns it if it panics, as documented above), so
+        //         they are transferred rather than leaked, and recording a
+        //         leak here would be a false positive
+        let (with, val) = unsafe { Disposable::take(this.0) };
+
+        DisposableWith::new(with, f(val))
+    }
+
+    /// Consume the wrapper, producing a [`Disposable`] containing the value to
+    /// dispose with and the contained value.
+    ///
+    /// [`Disposable`]: ./struct.Disposable.html
+    pub fn into_disposable(this: Self) -> Disposable<(W, T)> { this.0 }
+}
+
+impl<W, T: DisposeWith<W>> From<(W, T)> for DisposableWith<W, T> {
+    fn from((with, val): (W, T)) -> Self { Self::new(with, val) }
+}
+
+impl<W, T: DisposeWith<W>> From<Disposable<(W, T)>> for DisposableWith<W, T> {
+    fn from(val: Disposable<(W, T)>) -> Self { Self(val) }
+}
+
+impl<W: fmt::Debug, T: DisposeWith<W> + fmt::Debug> fmt::Debug for DisposableWith<W, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DisposableWith")
+            .field("with", &self.0 .0)
+            .field("val", &self.0 .1)
+            .finish()
+    }
+}
+
+impl<W, T: DisposeWith<W>> AsRef<T> for DisposableWith<W, T> {
+    fn as_ref(&self) -> &T { &self.0 .1 }
+}
+
+impl<W, T: DisposeWith<W>> AsMut<T> for DisposableWith<W, T> {
+    fn as_mut(&mut self) -> &mut T { &mut self.0 .1 }
+}
+
+impl<W, T: DisposeWith<W>> Borrow<T> for DisposableWith<W, T> {
+    fn borrow(&self) -> &T { self.as_ref() }
+}
+
+impl<W, T: DisposeWith<W>> BorrowMut<T> for DisposableWith<W, T> {
+    fn borrow_mut(&mut self) -> &mut T { self.as_mut() }
+}
+
+impl<W, T: DisposeWith<W>> Deref for DisposableWith<W, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T { self.as_ref() }
+}
+
+impl<W, T: DisposeWith<W>> DerefMut for DisposableWith<W, T> {
+    fn deref_mut(&mut self) -> &mut T { self.as_mut() }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use std::{cell::RefCell, vec::Vec};
+
+    use super::*;
+
+    struct Res {
+        id: u32,
+    }
+
+    impl DisposeWith<&RefCell<Vec<u32>>> for Res {
+        fn dispose_with(self, log: &RefCell<Vec<u32>>) { log.borrow_mut().push(self.id); }
+    }
+
+    #[test]
+    fn disposes_with() {
+        let log = RefCell::new(Vec::new());
+
+        {
+            let mut a = DisposableWith::new(&log, Res { id: 1 });
+            a.id += 1;
+
+            let b = DisposableWith::new(&log, Res { id: 3 });
+            let b = DisposableWith::map(b, |r| Res { id: r.id * 2 });
+            assert_eq!(DisposableWith::with(&b).as_ptr(), log.as_ptr());
+
+            let c = DisposableWith::new(&log, Res { id: 4 });
+            let (_, c) = unsafe { DisposableWith::into_parts(c) };
+            assert_eq!(c.id, 4);
+        }
+
+        assert_eq!(*log.borrow(), [6, 2]);
+    }
+}
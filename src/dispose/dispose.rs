@@ -1,4 +1,46 @@
-use super::DisposeWith;
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{unwind::Panics, DisposeWith};
+
+/// The reason a value is being disposed, as passed to
+/// [`Dispose::dispose_because`].
+///
+/// [`Dispose::dispose_because`]: ./trait.Dispose.html#method.dispose_because
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisposeReason {
+    /// The value is being disposed explicitly, such as by calling
+    /// [`Disposable::try_dispose`].
+    ///
+    /// [`Disposable::try_dispose`]: ./struct.Disposable.html#method.try_dispose
+    Explicit,
+    /// The value is being dropped normally at the end of its scope.
+    ScopeExit,
+    /// The value is being dropped while the current thread is unwinding from a
+    /// panic.
+    ///
+    /// Without the `std` feature, unwinding cannot be detected, and this
+    /// reason is never produced.
+    Unwind,
+}
+
+impl DisposeReason {
+    /// Returns the reason for a value being disposed by a `Drop` impl: either
+    /// [`Unwind`] if the current thread is panicking, or [`ScopeExit`]
+    /// otherwise.
+    ///
+    /// [`Unwind`]: ./enum.DisposeReason.html#variant.Unwind
+    /// [`ScopeExit`]: ./enum.DisposeReason.html#variant.ScopeExit
+    #[must_use]
+    pub fn on_drop() -> Self {
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return Self::Unwind;
+        }
+
+        Self::ScopeExit
+    }
+}
 
 /// A trait representing a standard "dispose" method for consuming an object at
 /// the end of its scope.
@@ -15,6 +57,47 @@ use super::DisposeWith;
 pub trait Dispose {
     /// Consume self and deinitialize its contents.
     fn dispose(self);
+
+    /// Consume self and deinitialize its contents, given the reason it is
+    /// being disposed.
+    ///
+    /// Types whose teardown depends on how their scope was exited (for
+    /// instance, committing a transaction on a normal exit but rolling it back
+    /// during a panic) can override this method.  [`Disposable`] calls it with
+    /// the appropriate [`DisposeReason`], and the containers in this crate
+    /// (as well as types using the derive macros) pass the reason they were
+    /// given on to their contents.  The default implementation ignores the
+    /// reason and calls [`dispose`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dispose::{Disposable, Dispose, DisposeReason};
+    ///
+    /// struct Transaction;
+    ///
+    /// impl Dispose for Transaction {
+    ///     fn dispose(self) { self.dispose_because(DisposeReason::Explicit) }
+    ///
+    ///     fn dispose_because(self, reason: DisposeReason) {
+    ///         match reason {
+    ///             DisposeReason::Unwind => println!("Rolling back"),
+    ///             _ => println!("Committing"),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let _tx = Disposable::new(Transaction); // Prints "Committing" when dropped
+    /// ```
+    ///
+    /// [`Disposable`]: ./struct.Disposable.html
+    /// [`DisposeReason`]: ./enum.DisposeReason.html
+    /// [`dispose`]: ./trait.Dispose.html#tymethod.dispose
+    fn dispose_because(self, reason: DisposeReason)
+    where Self: Sized {
+        let _ = reason;
+        self.dispose();
+    }
 }
 
 impl<F: FnOnce()> Dispose for F {
@@ -27,6 +110,8 @@ where (W, T): Sized
 {
     /// Dispose `self.1`, passing `self.0` to `dispose_with`.
     fn dispose(self) { self.1.dispose_with(self.0) }
+
+    fn dispose_because(self, reason: DisposeReason) { self.1.dispose_with_because(self.0, reason) }
 }
 
 /// A helper trait for iterators with items implementing `Dispose`.
@@ -38,35 +123,67 @@ where (W, T): Sized
 ///
 /// Several types with `DisposeIterator` implementations (such as [`Vec<T>`])
 /// have dedicated `Dispose` implementations for convenience.
+///
+/// # Panics
+/// If disposing an item panics, the remaining items are still disposed, after
+/// which the first panic is resumed.
 pub trait DisposeIterator {
     /// Dispose all items in the iterator, consuming it.
     fn dispose_iter(self);
+
+    /// Dispose all items in the iterator, consuming it and passing `reason` to
+    /// each item.  See [`Dispose::dispose_because`] for more info.
+    ///
+    /// The default implementation ignores the reason and calls
+    /// [`dispose_iter`].
+    ///
+    /// [`Dispose::dispose_because`]: ./trait.Dispose.html#method.dispose_because
+    /// [`dispose_iter`]: ./trait.DisposeIterator.html#tymethod.dispose_iter
+    fn dispose_iter_because(self, reason: DisposeReason)
+    where Self: Sized {
+        let _ = reason;
+        self.dispose_iter();
+    }
 }
 
 impl<I: IntoIterator> DisposeIterator for I
 where I::Item: Dispose
 {
-    fn dispose_iter(self) {
+    fn dispose_iter(self) { self.dispose_iter_because(DisposeReason::Explicit) }
+
+    fn dispose_iter_because(self, reason: DisposeReason) {
+        let mut panics = Panics::new();
+
         for el in self {
-            el.dispose();
+            panics.run(|| el.dispose_because(reason));
         }
+
+        panics.resume();
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<T> Dispose for Vec<T>
 where Vec<T>: DisposeIterator
 {
     fn dispose(self) { self.dispose_iter() }
+
+    fn dispose_because(self, reason: DisposeReason) { self.dispose_iter_because(reason) }
 }
 
+#[cfg(feature = "alloc")]
 impl<T> Dispose for Box<[T]>
 where Vec<T>: DisposeIterator
 {
     fn dispose(self) { self.into_vec().dispose_iter() }
+
+    fn dispose_because(self, reason: DisposeReason) { self.into_vec().dispose_iter_because(reason) }
 }
 
 impl<'a, T> Dispose for &'a [T]
 where &'a [T]: DisposeIterator
 {
     fn dispose(self) { self.dispose_iter() }
+
+    fn dispose_because(self, reason: DisposeReason) { self.dispose_iter_because(reason) }
 }
@@ -0,0 +1,224 @@
+use alloc::boxed::Box;
+use core::fmt;
+
+use crate::{
+    try_dispose::report_drop_error, Disposable, Dispose, DisposeReason, DisposeWith, TryDispose,
+};
+
+/// An object-safe companion to [`Dispose`].
+///
+/// Because [`Dispose::dispose`] consumes `self` by value, `Dispose` cannot be
+/// used as a trait object.  This trait is implemented for every type
+/// implementing `Dispose`, and instead disposes values through a `Box`,
+/// allowing collections of mixed resources to be stored as
+/// `Box<dyn DisposeDyn>`, which itself implements `Dispose`.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{Dispose, DisposeDyn, DisposeIterator};
+///
+/// struct Buffer;
+/// struct Image;
+///
+/// impl Dispose for Buffer {
+///     fn dispose(self) { println!("Freeing buffer"); }
+/// }
+///
+/// impl Dispose for Image {
+///     fn dispose(self) { println!("Freeing image"); }
+/// }
+///
+/// let resources: Vec<Box<dyn DisposeDyn>> = vec![Box::new(Buffer), Box::new(Image)];
+///
+/// resources.dispose_iter();
+/// ```
+///
+/// [`Dispose`]: ./trait.Dispose.html
+/// [`Dispose::dispose`]: ./trait.Dispose.html#tymethod.dispose
+pub trait DisposeDyn {
+    /// Consume the boxed value and deinitialize its contents.
+    fn dispose_boxed(self: Box<Self>);
+
+    /// Consume the boxed value and deinitialize its contents, given the reason
+    /// it is being disposed.
+    fn dispose_boxed_because(self: Box<Self>, reason: DisposeReason);
+}
+
+impl<T: Dispose> DisposeDyn for T {
+    fn dispose_boxed(self: Box<Self>) { (*self).dispose() }
+
+    fn dispose_boxed_because(self: Box<Self>, reason: DisposeReason) {
+        (*self).dispose_because(reason);
+    }
+}
+
+impl Dispose for Box<dyn DisposeDyn + '_> {
+    fn dispose(self) { self.dispose_boxed() }
+
+    fn dispose_because(self, reason: DisposeReason) { self.dispose_boxed_because(reason) }
+}
+
+impl Dispose for Box<dyn DisposeDyn + Send + '_> {
+    fn dispose(self) { self.dispose_boxed() }
+
+    fn dispose_because(self, reason: DisposeReason) { self.dispose_boxed_because(reason) }
+}
+
+/// An object-safe companion to [`DisposeWith`], similar to [`DisposeDyn`].
+///
+/// This trait is implemented for every type implementing `DisposeWith<W>`, and
+/// allows collections of mixed resources sharing the same context type to be
+/// stored as `Box<dyn DisposeWithDyn<W>>`, which itself implements
+/// `DisposeWith<W>`.
+///
+/// [`DisposeWith`]: ./trait.DisposeWith.html
+/// [`DisposeDyn`]: ./trait.DisposeDyn.html
+pub trait DisposeWithDyn<W> {
+    /// Consume the boxed value, using the provided value.
+    fn dispose_boxed_with(self: Box<Self>, with: W);
+
+    /// Consume the boxed value, using the provided value, given the reason it
+    /// is being disposed.
+    fn dispose_boxed_with_because(self: Box<Self>, with: W, reason: DisposeReason);
+}
+
+impl<W, T: DisposeWith<W>> DisposeWithDyn<W> for T {
+    fn dispose_boxed_with(self: Box<Self>, with: W) { (*self).dispose_with(with) }
+
+    fn dispose_boxed_with_because(self: Box<Self>, with: W, reason: DisposeReason) {
+        (*self).dispose_with_because(with, reason);
+    }
+}
+
+impl<W> DisposeWith<W> for Box<dyn DisposeWithDyn<W> + '_> {
+    fn dispose_with(self, with: W) { self.dispose_boxed_with(with) }
+
+    fn dispose_with_because(self, with: W, reason: DisposeReason) {
+        self.dispose_boxed_with_because(with, reason);
+    }
+}
+
+impl<W> DisposeWith<W> for Box<dyn DisposeWithDyn<W> + Send + '_> {
+    fn dispose_with(self, with: W) { self.dispose_boxed_with(with) }
+
+    fn dispose_with_because(self, with: W, reason: DisposeReason) {
+        self.dispose_boxed_with_because(with, reason);
+    }
+}
+
+/// A type-erased [`Disposable`], able to hold a value of any type implementing
+/// [`Dispose`] or [`TryDispose`].
+///
+/// Any `Disposable<T>` can be converted into a `DynDisposable` using `From`,
+/// which allows resources of different types to be stored together without
+/// having to [`leak`] them from their original wrappers.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{defer, DynDisposable};
+///
+/// let guards: Vec<DynDisposable> = vec![
+///     defer(|| println!("first")).into(),
+///     DynDisposable::new(|| println!("second")),
+/// ];
+///
+/// drop(guards); // Prints "first", then "second"
+/// ```
+///
+/// [`Disposable`]: ./struct.Disposable.html
+/// [`Dispose`]: ./trait.Dispose.html
+/// [`TryDispose`]: ./trait.TryDispose.html
+/// [`leak`]: ./struct.Disposable.html#method.leak
+pub struct DynDisposable<'a>(Disposable<Box<dyn DisposeDyn + 'a>>);
+
+impl<'a> DynDisposable<'a> {
+    /// Construct a new `DynDisposable`, wrapping around `val`.
+    pub fn new<T: Dispose + 'a>(val: T) -> Self { Self::from_box(Box::new(val)) }
+
+    /// Construct a new `DynDisposable` from an already-boxed value.
+    #[must_use]
+    pub fn from_box(val: Box<dyn DisposeDyn + 'a>) -> Self { Self(Disposable::new(val)) }
+
+    /// Consume the wrapper, producing the contained value.
+    ///
+    /// # Safety
+    ///
+    /// See [`Disposable::leak`].
+    ///
+    /// [`Disposable::leak`]: ./struct.Disposable.html#method.leak
+    #[must_use]
+    pub unsafe fn leak(this: Self) -> Box<dyn DisposeDyn + 'a> { Disposable::leak(this.0) }
+}
+
+impl<'a, T: TryDispose + 'a> From<Disposable<T>> for DynDisposable<'a> {
+    /// Erase the type of `val`.  Any error produced while disposing it is
+    /// passed to the drop error hook, as if `val` itself had been dropped.
+    fn from(val: Disposable<T>) -> Self { Self::new(ForwardDisposable(val)) }
+}
+
+/// Adapts a [`Disposable`] to [`Dispose`], so that it can be type-erased
+/// while still receiving the reason it is disposed with.  Any error produced
+/// is passed to the drop error hook.
+pub(crate) struct ForwardDisposable<T: TryDispose>(pub Disposable<T>);
+
+impl<T: TryDispose> Dispose for ForwardDisposable<T> {
+    fn dispose(self) { self.dispose_because(DisposeReason::Explicit) }
+
+    fn dispose_because(self, reason: DisposeReason) {
+        // SAFETY: the value is disposed immediately
+        if let Err(e) = unsafe { Disposable::take(self.0) }.try_dispose_because(reason) {
+            report_drop_error::<T>(&e);
+        }
+    }
+}
+
+impl fmt::Debug for DynDisposable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynDisposable").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, vec, vec::Vec};
+
+    use super::*;
+    use crate::DisposeIterator;
+
+    struct Res<'a>(u32, &'a RefCell<Vec<u32>>);
+
+    impl Dispose for Res<'_> {
+        fn dispose(self) { self.1.borrow_mut().push(self.0); }
+    }
+
+    #[test]
+    fn boxed_dispose() {
+        let log = RefCell::new(Vec::new());
+
+        let res: Vec<Box<dyn DisposeDyn>> = vec![
+            Box::new(Res(0, &log)),
+            Box::new(|| log.borrow_mut().push(1)),
+            Box::new(Res(2, &log)),
+        ];
+
+        res.dispose_iter();
+        assert_eq!(*log.borrow(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn erased_disposable() {
+        let log = RefCell::new(Vec::new());
+
+        {
+            let _a = DynDisposable::from(Disposable::new(Res(0, &log)));
+            let _b = DynDisposable::new(Res(1, &log));
+            let c = DynDisposable::new(Res(2, &log));
+
+            unsafe { DynDisposable::leak(c) }.dispose();
+        }
+
+        assert_eq!(*log.borrow(), [2, 1, 0]);
+    }
+}
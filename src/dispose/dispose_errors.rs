@@ -0,0 +1,109 @@
+use alloc::{borrow::Cow, boxed::Box, vec::Vec};
+use core::{error::Error, fmt};
+
+/// An aggregate error produced when one or more values fail to dispose.
+///
+/// This is the error type used by the [`TryDispose`] derive macro and by
+/// [`TryDisposeIterator`], both of which continue disposing the remaining
+/// values after a failure.  Each error is labeled with the name of the field
+/// (or the index of the item) that produced it.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::DisposeErrors;
+///
+/// let mut errors = DisposeErrors::new();
+///
+/// errors.record("header", Ok::<(), &str>(()));
+/// errors.record("body", Err("disk full"));
+///
+/// let err = errors.into_result().unwrap_err();
+///
+/// assert_eq!(err.len(), 1);
+/// assert_eq!(err.to_string(), "failed to dispose `body`: \"disk full\"");
+/// ```
+///
+/// [`TryDispose`]: ./derive.TryDispose.html
+/// [`TryDisposeIterator`]: ./trait.TryDisposeIterator.html
+#[derive(Default)]
+pub struct DisposeErrors(Vec<(Cow<'static, str>, Box<dyn fmt::Debug + Send + Sync>)>);
+
+impl DisposeErrors {
+    /// Construct a new, empty error list.
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
+
+    /// Add an error to the list, labeled with `name`.
+    pub fn push(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        error: impl fmt::Debug + Send + Sync + 'static,
+    ) {
+        self.0.push((name.into(), Box::new(error)));
+    }
+
+    /// Add the error contained in `result` to the list, if there is one.
+    pub fn record<E: fmt::Debug + Send + Sync + 'static>(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        result: Result<(), E>,
+    ) {
+        if let Err(e) = result {
+            self.push(name, e);
+        }
+    }
+
+    /// Returns true if no errors have been recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Returns the number of errors recorded.
+    #[must_use]
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Iterate over the recorded errors, along with the name of the value that
+    /// produced each one.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &(dyn fmt::Debug + Send + Sync))> {
+        self.0.iter().map(|(n, e)| (&**n, &**e))
+    }
+
+    /// Convert the list into a `Result`, returning `Ok(())` if no errors were
+    /// recorded.
+    ///
+    /// # Errors
+    /// This function returns `self` if it contains any errors.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Debug for DisposeErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl fmt::Display for DisposeErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self.0 {
+            [] => f.write_str("no disposal errors"),
+            [(name, err)] => write!(f, "failed to dispose `{name}`: {err:?}"),
+            errs => {
+                write!(f, "failed to dispose {} values", errs.len())?;
+
+                for (name, err) in errs {
+                    write!(f, "; `{name}`: {err:?}")?;
+                }
+
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Error for DisposeErrors {}
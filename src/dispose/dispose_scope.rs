@@ -0,0 +1,184 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{cell::RefCell, fmt, marker::PhantomData, ptr::NonNull};
+
+use crate::{Disposable, Dispose, DisposeIteratorWith, DisposeReason, DisposeWith, DisposeWithDyn};
+
+type Entry<'ctx, W> = NonNull<dyn DisposeWithDyn<&'ctx W> + 'ctx>;
+
+/// An arena of values that all share a single disposal context.
+///
+/// Resources such as per-frame GPU objects are often allocated in large
+/// numbers, and all need the same value (such as a `&Device`) to be destroyed.
+/// A `DisposeScope` holds a reference to that value, and accepts any value
+/// implementing [`DisposeWith<&W>`] through [`alloc`], which moves it into the
+/// arena and returns a reference to it that is valid for as long as the scope
+/// is borrowed.  When the scope is disposed, every value in it is disposed in
+/// reverse order of allocation using [`DisposeIteratorWith`], passing each the
+/// shared context.  As with `DisposeIteratorWith`, all values are disposed even
+/// if one of them panics.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{DisposeScope, DisposeWith};
+///
+/// struct Device;
+/// struct Buffer(u32);
+/// struct Image(u32);
+///
+/// impl DisposeWith<&Device> for Buffer {
+///     fn dispose_with(self, _: &Device) { println!("freeing buffer {}", self.0); }
+/// }
+///
+/// impl DisposeWith<&Device> for Image {
+///     fn dispose_with(self, _: &Device) { println!("freeing image {}", self.0); }
+/// }
+///
+/// let dev = Device;
+///
+/// {
+///     let frame = DisposeScope::new(&dev);
+///
+///     let buf = frame.alloc(Buffer(1));
+///     let img = frame.alloc(Image(2));
+///     buf.0 += img.0;
+///
+///     assert_eq!(frame.len(), 2);
+/// } // Prints "freeing image 2", then "freeing buffer 3"
+/// ```
+///
+/// [`DisposeWith<&W>`]: ./trait.DisposeWith.html
+/// [`alloc`]: ./struct.DisposeScope.html#method.alloc
+/// [`DisposeIteratorWith`]: ./trait.DisposeIteratorWith.html
+pub struct DisposeScope<'ctx, W: ?Sized> {
+    ctx: &'ctx W,
+    // Each entry is created by Box::leak in alloc, and reclaimed exactly once
+    // with Box::from_raw when the scope is disposed or dropped, both of which
+    // empty the list
+    entries: RefCell<Vec<Entry<'ctx, W>>>,
+    _own: PhantomData<Box<dyn DisposeWithDyn<&'ctx W> + 'ctx>>,
+}
+
+impl<'ctx, W: ?Sized> DisposeScope<'ctx, W> {
+    /// Construct a new, empty scope, disposing its contents with `ctx`.
+    pub fn new(ctx: &'ctx W) -> Disposable<Self> {
+        Disposable::new(Self {
+            ctx,
+            entries: RefCell::new(Vec::new()),
+            _own: PhantomData,
+        })
+    }
+
+    /// Returns the context shared by every value in the scope.
+    #[must_use]
+    pub fn context(&self) -> &'ctx W { self.ctx }
+
+    /// Returns the number of values in the scope.
+    #[must_use]
+    pub fn len(&self) -> usize { self.entries.borrow().len() }
+
+    /// Returns true if the scope contains no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool { self.entries.borrow().is_empty() }
+
+    /// Move `val` into the scope, returning a reference to it.  It will be
+    /// disposed with the scope's context when the scope is disposed.
+    #[allow(clippy::mut_from_ref)] // Each value is boxed separately
+    pub fn alloc<T: DisposeWith<&'ctx W> + 'ctx>(&self, val: T) -> &mut T {
+        let ptr = NonNull::from(Box::leak(Box::new(val)));
+        self.entries.borrow_mut().push(ptr);
+
+        // SAFETY: the box is owned by the scope and is not freed or accessed
+        //         again until the scope is consumed or dropped (see entries),
+        //         which requires that this borrow has ended
+        unsafe { &mut *ptr.as_ptr() }
+    }
+}
+
+impl<W: ?Sized> Dispose for DisposeScope<'_, W> {
+    fn dispose(self) { self.dispose_because(DisposeReason::Explicit) }
+
+    fn dispose_because(self, reason: DisposeReason) {
+        let entries = self.entries.take();
+
+        // SAFETY: each pointer came from Box::leak in alloc, and is reclaimed
+        //         only here, since the list was taken above and the scope's
+        //         Drop impl will find it empty
+        entries
+            .into_iter()
+            .rev()
+            .map(|p| unsafe { Box::from_raw(p.as_ptr()) })
+            .dispose_iter_with_because(self.ctx, reason);
+    }
+}
+
+impl<W: ?Sized> Drop for DisposeScope<'_, W> {
+    /// Drop any values remaining in the scope without disposing them.
+    fn drop(&mut self) {
+        for p in self.entries.get_mut().drain(..).rev() {
+            // SAFETY: each pointer came from Box::leak in alloc, and is
+            //         reclaimed only here, since it is drained from the list
+            drop(unsafe { Box::from_raw(p.as_ptr()) });
+        }
+    }
+}
+
+impl<W: ?Sized> fmt::Debug for DisposeScope<'_, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DisposeScope")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use std::{
+        cell::RefCell,
+        panic::{catch_unwind, AssertUnwindSafe},
+        vec::Vec,
+    };
+
+    use super::*;
+
+    struct Res(u32);
+
+    impl DisposeWith<&RefCell<Vec<u32>>> for Res {
+        fn dispose_with(self, log: &RefCell<Vec<u32>>) {
+            assert!(self.0 != 0, "oh no");
+            log.borrow_mut().push(self.0);
+        }
+    }
+
+    #[test]
+    fn scope_order() {
+        let log = RefCell::new(Vec::new());
+
+        {
+            let scope = DisposeScope::new(&log);
+            let a = scope.alloc(Res(1));
+            let b = scope.alloc(Res(2));
+            scope.alloc(|log: &RefCell<Vec<u32>>| log.borrow_mut().push(3));
+
+            a.0 += 10;
+            b.0 += 10;
+        }
+
+        assert_eq!(*log.borrow(), [3, 12, 11]);
+    }
+
+    #[test]
+    fn scope_panic() {
+        let log = RefCell::new(Vec::new());
+
+        catch_unwind(AssertUnwindSafe(|| {
+            let scope = DisposeScope::new(&log);
+            scope.alloc(Res(1));
+            scope.alloc(Res(0));
+            scope.alloc(Res(2));
+        }))
+        .unwrap_err();
+
+        assert_eq!(*log.borrow(), [2, 1]);
+    }
+}
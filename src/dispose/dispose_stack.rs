@@ -0,0 +1,490 @@
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::{align_of, size_of, ManuallyDrop, MaybeUninit},
+    ptr,
+};
+
+#[cfg(feature = "alloc")]
+use crate::{dispose_dyn::ForwardDisposable, DisposeDyn, DynDisposable, TryDispose};
+use crate::{Disposable, Dispose, DisposeIterator, DisposeReason};
+
+/// A dynamically-sized stack of values to dispose, akin to Python's
+/// `contextlib.ExitStack`.
+///
+/// [`defer`] and [`Disposable`] work well when the number of resources in a
+/// scope is known statically, but setup code that conditionally acquires any
+/// number of resources of different types needs somewhere to put them.  A
+/// `DisposeStack` accepts any value implementing [`Dispose`] (including
+/// closures), and disposes everything it holds in reverse order when it is
+/// disposed.
+///
+/// Once setup has succeeded, [`pop_all`] can be used to transfer ownership of
+/// every value on the stack to a new stack, so that they outlive the scope in
+/// which they were acquired.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{DisposeStack, Disposable};
+///
+/// fn open_all(names: &[&'static str]) -> Result<Disposable<DisposeStack<'static>>, String> {
+///     let mut stack = DisposeStack::new();
+///
+///     for &name in names {
+///         if name.is_empty() {
+///             return Err("bad name".into()); // Everything opened so far is closed here
+///         }
+///
+///         println!("opening {name}");
+///         stack.push(move || println!("closing {name}"));
+///     }
+///
+///     Ok(stack.pop_all())
+/// }
+///
+/// let files = open_all(&["a", "b", "c"]).unwrap();
+///
+/// assert_eq!(files.len(), 3);
+/// drop(files); // Prints "closing c", "closing b", then "closing a"
+///
+/// assert!(open_all(&["a", "", "c"]).is_err());
+/// ```
+///
+/// [`defer`]: ./fn.defer.html
+/// [`Disposable`]: ./struct.Disposable.html
+/// [`Dispose`]: ./trait.Dispose.html
+/// [`pop_all`]: ./struct.DisposeStack.html#method.pop_all
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+pub struct DisposeStack<'a>(Vec<Box<dyn DisposeDyn + 'a>>);
+
+#[cfg(feature = "alloc")]
+impl<'a> DisposeStack<'a> {
+    /// Construct a new, empty stack.
+    #[must_use]
+    pub fn new() -> Disposable<Self> { Self::default().into() }
+
+    /// Returns the number of values on the stack.
+    #[must_use]
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Returns true if the stack is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Push a value onto the stack, to be disposed before any values already
+    /// on the stack.
+    pub fn push<T: Dispose + 'a>(&mut self, val: T) { self.0.push(Box::new(val)); }
+
+    /// Push a value already wrapped in a [`Disposable`] onto the stack.
+    ///
+    /// Since a `Disposable` disposes its contents whenever it is dropped, the
+    /// value is still disposed if the stack is [cancelled][`cancel`].
+    ///
+    /// [`Disposable`]: ./struct.Disposable.html
+    /// [`cancel`]: ./struct.DisposeStack.html#method.cancel
+    pub fn push_disposable<T: TryDispose + 'a>(&mut self, val: Disposable<T>) {
+        self.0.push(Box::new(ForwardDisposable(val)));
+    }
+
+    /// Remove the most recently pushed value from the stack.
+    ///
+    /// The value is returned as a [`DynDisposable`], so dropping the result
+    /// disposes the value immediately.
+    ///
+    /// [`DynDisposable`]: ./struct.DynDisposable.html
+    pub fn pop(&mut self) -> Option<DynDisposable<'a>> { self.0.pop().map(DynDisposable::from_box) }
+
+    /// Remove every value from the stack _without_ disposing them.
+    ///
+    /// The values are dropped normally instead, so this is mainly useful for
+    /// discarding rollback actions once an operation has succeeded.  Note that
+    /// dropping a value can still dispose it: values added with
+    /// [`push_disposable`], or any other values which dispose themselves on
+    /// drop (such as closures capturing a [`Disposable`]), are disposed as
+    /// usual.
+    ///
+    /// [`push_disposable`]: ./struct.DisposeStack.html#method.push_disposable
+    /// [`Disposable`]: ./struct.Disposable.html
+    pub fn cancel(&mut self) { self.0.clear(); }
+
+    /// Move every value on the stack to a new stack, leaving this one empty.
+    #[must_use = "Dropping the returned stack will dispose its contents immediately."]
+    pub fn pop_all(&mut self) -> Disposable<Self> { Self(core::mem::take(&mut self.0)).into() }
+}
+
+#[cfg(feature = "alloc")]
+impl Dispose for DisposeStack<'_> {
+    /// Dispose every value on the stack, from most to least recently pushed.
+    fn dispose(self) { self.0.into_iter().rev().dispose_iter() }
+
+    fn dispose_because(self, reason: DisposeReason) {
+        self.0.into_iter().rev().dispose_iter_because(reason);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for DisposeStack<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DisposeStack")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A single type-erased value stored inline in an [`InlineDisposeStack`].
+///
+/// Values of this type are produced by [`InlineDisposeStack::pop`].  Dropping
+/// an entry drops the value it contains without disposing it.
+///
+/// [`InlineDisposeStack`]: ./struct.InlineDisposeStack.html
+/// [`InlineDisposeStack::pop`]: ./struct.InlineDisposeStack.html#method.pop
+pub struct InlineEntry<'a, const WORDS: usize> {
+    data: [MaybeUninit<usize>; WORDS],
+    dispose: unsafe fn(*mut (), DisposeReason),
+    drop: unsafe fn(*mut ()),
+    _m: PhantomData<(&'a (), *const ())>,
+}
+
+impl<'a, const WORDS: usize> InlineEntry<'a, WORDS> {
+    fn new<T: Dispose + 'a>(val: T) -> Self {
+        const {
+            assert!(
+                size_of::<T>() <= size_of::<[usize; WORDS]>()
+                    && align_of::<T>() <= align_of::<usize>(),
+                "Value is too large or too strictly aligned to fit in an InlineDisposeStack slot",
+            );
+        };
+
+        let mut data = [MaybeUninit::uninit(); WORDS];
+        // SAFETY: the assertion above guarantees data is large and aligned
+        //         enough to hold a T
+        unsafe { data.as_mut_ptr().cast::<T>().write(val) };
+
+        Self {
+            data,
+            dispose: dispose_erased::<T>,
+            drop: drop_erased::<T>,
+            _m: PhantomData,
+        }
+    }
+}
+
+unsafe fn dispose_erased<T: Dispose>(ptr: *mut (), reason: DisposeReason) {
+    ptr::read(ptr.cast::<T>()).dispose_because(reason);
+}
+
+unsafe fn drop_erased<T>(ptr: *mut ()) { ptr::drop_in_place(ptr.cast::<T>()); }
+
+impl<const WORDS: usize> Dispose for InlineEntry<'_, WORDS> {
+    fn dispose(self) { self.dispose_because(DisposeReason::Explicit) }
+
+    fn dispose_because(self, reason: DisposeReason) {
+        let mut this = ManuallyDrop::new(self);
+
+        // SAFETY: data contains a value of the type this.dispose expects,
+        //         and it will not be accessed again
+        unsafe { (this.dispose)(this.data.as_mut_ptr().cast(), reason) };
+    }
+}
+
+impl<const WORDS: usize> Drop for InlineEntry<'_, WORDS> {
+    fn drop(&mut self) {
+        // SAFETY: data contains a value of the type self.drop expects, and it
+        //         will not be accessed again
+        unsafe { (self.drop)(self.data.as_mut_ptr().cast()) };
+    }
+}
+
+impl<const WORDS: usize> fmt::Debug for InlineEntry<'_, WORDS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InlineEntry").finish_non_exhaustive()
+    }
+}
+
+/// A fixed-capacity variant of [`DisposeStack`] that stores its contents
+/// inline, without allocating.
+///
+/// The stack holds up to `N` values, each of which must fit within `WORDS`
+/// machine words and require no stricter alignment than a `usize`.  Pushing a
+/// value that is too large is a compile-time error, and pushing a value onto a
+/// full stack returns it to the caller.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::InlineDisposeStack;
+///
+/// let mut stack = InlineDisposeStack::<4>::new();
+///
+/// assert!(stack.push(|| println!("world!")).is_ok());
+/// assert!(stack.push(|| println!("Hello, ")).is_ok());
+///
+/// drop(stack); // Prints "Hello, world!"
+/// ```
+///
+/// [`DisposeStack`]: ./struct.DisposeStack.html
+pub struct InlineDisposeStack<'a, const N: usize, const WORDS: usize = 4> {
+    entries: [MaybeUninit<InlineEntry<'a, WORDS>>; N],
+    len: usize,
+}
+
+impl<'a, const N: usize, const WORDS: usize> InlineDisposeStack<'a, N, WORDS> {
+    /// Construct a new, empty stack.
+    #[must_use]
+    pub fn new() -> Disposable<Self> { Self::default().into() }
+
+    /// Returns the number of values on the stack.
+    #[must_use]
+    pub fn len(&self) -> usize { self.len }
+
+    /// Returns true if the stack is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Returns true if the stack cannot hold any more values.
+    #[must_use]
+    pub fn is_full(&self) -> bool { self.len == N }
+
+    /// Push a value onto the stack, to be disposed before any values already
+    /// on the stack.
+    ///
+    /// # Errors
+    /// If the stack is full, `val` is returned.
+    pub fn push<T: Dispose + 'a>(&mut self, val: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(val);
+        }
+
+        self.entries[self.len].write(InlineEntry::new(val));
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove the most recently pushed value from the stack.
+    ///
+    /// The value is returned as a [`Disposable`], so dropping the result
+    /// disposes the value immediately.
+    ///
+    /// [`Disposable`]: ./struct.Disposable.html
+    pub fn pop(&mut self) -> Option<Disposable<InlineEntry<'a, WORDS>>> {
+        self.len = self.len.checked_sub(1)?;
+
+        // SAFETY: all entries below the old length are initialized, and the
+        //         length has already been decremented
+        Some(unsafe { self.entries[self.len].assume_init_read() }.into())
+    }
+
+    /// Remove every value from the stack _without_ disposing them.
+    ///
+    /// The values are dropped normally instead, so as with
+    /// [`DisposeStack::cancel`], any values which dispose themselves on drop
+    /// (such as closures capturing a [`Disposable`]) are still disposed.
+    ///
+    /// [`DisposeStack::cancel`]: ./struct.DisposeStack.html#method.cancel
+    /// [`Disposable`]: ./struct.Disposable.html
+    pub fn cancel(&mut self) {
+        while let Some(entry) = self.pop() {
+            // SAFETY: the entry is dropped, but never disposed
+            drop(unsafe { Disposable::take(entry) });
+        }
+    }
+
+    /// Move every value on the stack to a new stack, leaving this one empty.
+    #[must_use = "Dropping the returned stack will dispose its contents immediately."]
+    pub fn pop_all(&mut self) -> Disposable<Self> {
+        let ret = Self {
+            // SAFETY: MaybeUninit does not require initialization
+            entries: unsafe { ptr::read(&raw const self.entries) },
+            len: self.len,
+        };
+
+        self.len = 0;
+        ret.into()
+    }
+}
+
+impl<const N: usize, const WORDS: usize> Default for InlineDisposeStack<'_, N, WORDS> {
+    fn default() -> Self {
+        Self {
+            entries: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize, const WORDS: usize> Dispose for InlineDisposeStack<'_, N, WORDS> {
+    /// Dispose every value on the stack, from most to least recently pushed.
+    fn dispose(self) { self.dispose_because(DisposeReason::Explicit) }
+
+    fn dispose_because(mut self, reason: DisposeReason) {
+        let len = self.len;
+        self.len = 0;
+
+        // SAFETY: all entries below len are initialized, and the length has
+        //         already been reset
+        self.entries[..len]
+            .iter()
+            .rev()
+            .map(|e| unsafe { e.assume_init_read() })
+            .dispose_iter_because(reason);
+    }
+}
+
+impl<const N: usize, const WORDS: usize> Drop for InlineDisposeStack<'_, N, WORDS> {
+    fn drop(&mut self) { self.cancel(); }
+}
+
+impl<const N: usize, const WORDS: usize> fmt::Debug for InlineDisposeStack<'_, N, WORDS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InlineDisposeStack")
+            .field("len", &self.len)
+            .field("capacity", &N)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, vec::Vec};
+
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn stack_order() {
+        let log = RefCell::new(Vec::new());
+
+        {
+            let mut stack = DisposeStack::new();
+
+            for i in 0..4 {
+                let log = &log;
+                stack.push(move || log.borrow_mut().push(i));
+            }
+
+            drop(stack.pop());
+            assert_eq!(*log.borrow(), [3]);
+
+            let stack2 = stack.pop_all();
+            assert!(stack.is_empty());
+            assert_eq!(stack2.len(), 3);
+        }
+
+        assert_eq!(*log.borrow(), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn stack_cancel() {
+        let log = RefCell::new(Vec::new());
+
+        {
+            let mut stack = DisposeStack::new();
+            stack.push(|| log.borrow_mut().push(0));
+            stack.cancel();
+        }
+
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn cancel_disposes_disposables() {
+        let log = RefCell::new(Vec::new());
+
+        {
+            let mut stack = DisposeStack::new();
+            stack.push(|| log.borrow_mut().push("pushed"));
+            stack.push_disposable(Disposable::new(|| log.borrow_mut().push("disposable")));
+            stack.cancel();
+
+            let mut inline = InlineDisposeStack::<2>::new();
+            let inner = Disposable::new(|| log.borrow_mut().push("inline disposable"));
+            inline.push(|| log.borrow_mut().push("inline")).ok();
+            inline.push(move || drop(inner)).ok();
+            inline.cancel();
+        }
+
+        assert_eq!(*log.borrow(), ["disposable", "inline disposable"]);
+    }
+
+    #[test]
+    fn inline_stack() {
+        let log = RefCell::new(Vec::new());
+
+        {
+            let mut stack = InlineDisposeStack::<3>::new();
+
+            for i in 0..3 {
+                let log = &log;
+                assert!(stack.push(move || log.borrow_mut().push(i)).is_ok());
+            }
+
+            assert!(stack.push(|| ()).is_err());
+
+            drop(stack.pop());
+            assert_eq!(*log.borrow(), [2]);
+
+            let _stack2 = stack.pop_all();
+            assert!(stack.is_empty());
+        }
+
+        assert_eq!(*log.borrow(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn inline_cancel_drops() {
+        let log = std::rc::Rc::new(());
+
+        {
+            let mut stack = InlineDisposeStack::<1>::new();
+            let log = std::rc::Rc::clone(&log);
+            stack.push(move || drop(log)).ok();
+            stack.cancel();
+        }
+
+        assert_eq!(std::rc::Rc::strong_count(&log), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn reasons_forwarded() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        use crate::ReasonFn;
+
+        let log = RefCell::new(Vec::new());
+        let push = |r| log.borrow_mut().push(r);
+
+        catch_unwind(AssertUnwindSafe(|| {
+            let mut stack = DisposeStack::new();
+            stack.push(ReasonFn(push));
+
+            let mut inline = InlineDisposeStack::<1>::new();
+            inline.push(ReasonFn(push)).ok();
+
+            panic!("oh no");
+        }))
+        .unwrap_err();
+
+        {
+            let mut inline = InlineDisposeStack::<1>::new();
+            inline.push(ReasonFn(push)).ok();
+        }
+
+        let mut stack = DisposeStack::new();
+        stack.push(ReasonFn(push));
+        Disposable::try_dispose(stack).unwrap();
+
+        assert_eq!(*log.borrow(), [
+            DisposeReason::Unwind,
+            DisposeReason::Unwind,
+            DisposeReason::ScopeExit,
+            DisposeReason::Explicit,
+        ]);
+    }
+}
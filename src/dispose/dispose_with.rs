@@ -1,3 +1,8 @@
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{unwind::Panics, DisposeReason};
+
 /// A helper trait for objects that must be consumed with the help of another
 /// value.
 ///
@@ -11,6 +16,20 @@
 pub trait DisposeWith<W> {
     /// Dispose self, using the provided value.
     fn dispose_with(self, with: W);
+
+    /// Dispose self, using the provided value, given the reason it is being
+    /// disposed.  See [`Dispose::dispose_because`] for more info.
+    ///
+    /// The default implementation ignores the reason and calls
+    /// [`dispose_with`].
+    ///
+    /// [`Dispose::dispose_because`]: ./trait.Dispose.html#method.dispose_because
+    /// [`dispose_with`]: ./trait.DisposeWith.html#tymethod.dispose_with
+    fn dispose_with_because(self, with: W, reason: DisposeReason)
+    where Self: Sized {
+        let _ = reason;
+        self.dispose_with(with);
+    }
 }
 
 impl<W, F: FnOnce(W)> DisposeWith<W> for F {
@@ -25,11 +44,28 @@ impl<W, F: FnOnce(W)> DisposeWith<W> for F {
 /// with other implementations.  More info can be found in the documentation for
 /// [`DisposeIterator`].
 ///
+/// As with [`DisposeIterator`], all items are disposed even if one of them
+/// panics.
+///
 /// [`DisposeIterator`]: ./trait.DisposeIterator.html
 pub trait DisposeIteratorWith<W> {
     /// Dispose all items in the iterator using the provided value, consuming
     /// both.
     fn dispose_iter_with(self, with: W);
+
+    /// Dispose all items in the iterator using the provided value, passing
+    /// `reason` to each item.  See [`Dispose::dispose_because`] for more info.
+    ///
+    /// The default implementation ignores the reason and calls
+    /// [`dispose_iter_with`].
+    ///
+    /// [`Dispose::dispose_because`]: ./trait.Dispose.html#method.dispose_because
+    /// [`dispose_iter_with`]: ./trait.DisposeIteratorWith.html#tymethod.dispose_iter_with
+    fn dispose_iter_with_because(self, with: W, reason: DisposeReason)
+    where Self: Sized {
+        let _ = reason;
+        self.dispose_iter_with(with);
+    }
 }
 
 impl<W: Copy, I: IntoIterator> DisposeIteratorWith<W> for I
@@ -37,29 +73,51 @@ where I::Item: DisposeWith<W>
 {
     /// Dispose all items in the iterator, using copies of the provided value.
     fn dispose_iter_with(self, with: W) {
+        self.dispose_iter_with_because(with, DisposeReason::Explicit);
+    }
+
+    fn dispose_iter_with_because(self, with: W, reason: DisposeReason) {
+        let mut panics = Panics::new();
+
         for el in self {
-            el.dispose_with(with);
+            panics.run(|| el.dispose_with_because(with, reason));
         }
+
+        panics.resume();
     }
 }
 
 // TODO: add a more relaxed Clone impl whenever trait specialization is
 // stabilized
 
+#[cfg(feature = "alloc")]
 impl<W, T> DisposeWith<W> for Vec<T>
 where Vec<T>: DisposeIteratorWith<W>
 {
     fn dispose_with(self, with: W) { self.dispose_iter_with(with) }
+
+    fn dispose_with_because(self, with: W, reason: DisposeReason) {
+        self.dispose_iter_with_because(with, reason);
+    }
 }
 
+#[cfg(feature = "alloc")]
 impl<W, T> DisposeWith<W> for Box<[T]>
 where Vec<T>: DisposeIteratorWith<W>
 {
     fn dispose_with(self, with: W) { self.into_vec().dispose_iter_with(with) }
+
+    fn dispose_with_because(self, with: W, reason: DisposeReason) {
+        self.into_vec().dispose_iter_with_because(with, reason);
+    }
 }
 
 impl<'a, W, T> DisposeWith<W> for &'a [T]
 where &'a [T]: DisposeIteratorWith<W>
 {
     fn dispose_with(self, with: W) { self.dispose_iter_with(with) }
+
+    fn dispose_with_because(self, with: W, reason: DisposeReason) {
+        self.dispose_iter_with_because(with, reason);
+    }
 }
@@ -0,0 +1,245 @@
+use core::{
+    any::{type_name, Any},
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU8, Ordering},
+};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{hook::Hook, Dispose, DisposeReason};
+
+/// What to do when disposing a value inside `Disposable::drop` panics while
+/// the thread is already unwinding from another panic.
+///
+/// Panicking during an unwind normally aborts the process with little
+/// indication of what went wrong.  The policy set with
+/// [`set_drop_panic_policy`] allows such panics to be caught instead.  Panics
+/// raised while the thread is not unwinding are always propagated.
+///
+/// [`set_drop_panic_policy`]: ./fn.set_drop_panic_policy.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(u8)]
+pub enum DropPanicPolicy {
+    /// Let the panic escape, aborting the process.  This is the default.
+    #[default]
+    Propagate = 0,
+    /// Catch the panic and print a message naming the type to standard error.
+    Log = 1,
+    /// Catch the panic and pass it to the hook registered with
+    /// [`set_drop_panic_hook`].
+    ///
+    /// [`set_drop_panic_hook`]: ./fn.set_drop_panic_hook.html
+    Report = 2,
+    /// Print a message naming the type to standard error, then abort the
+    /// process using the handler registered with [`set_abort_handler`].
+    ///
+    /// [`set_abort_handler`]: ./fn.set_abort_handler.html
+    Abort = 3,
+}
+
+impl DropPanicPolicy {
+    fn from_u8(val: u8) -> Self {
+        match val {
+            1 => Self::Log,
+            2 => Self::Report,
+            3 => Self::Abort,
+            _ => Self::Propagate,
+        }
+    }
+}
+
+static DROP_PANIC_POLICY: AtomicU8 = AtomicU8::new(DropPanicPolicy::Propagate as u8);
+
+/// Set the global [`DropPanicPolicy`], returning the previous policy.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{set_drop_panic_policy, DropPanicPolicy};
+///
+/// set_drop_panic_policy(DropPanicPolicy::Log);
+/// ```
+///
+/// [`DropPanicPolicy`]: ./enum.DropPanicPolicy.html
+pub fn set_drop_panic_policy(policy: DropPanicPolicy) -> DropPanicPolicy {
+    DropPanicPolicy::from_u8(DROP_PANIC_POLICY.swap(policy as u8, Ordering::AcqRel))
+}
+
+/// Returns the current global [`DropPanicPolicy`].
+///
+/// [`DropPanicPolicy`]: ./enum.DropPanicPolicy.html
+#[must_use]
+pub fn drop_panic_policy() -> DropPanicPolicy {
+    DropPanicPolicy::from_u8(DROP_PANIC_POLICY.load(Ordering::Acquire))
+}
+
+/// Information about a panic caught while disposing a value.
+///
+/// This is passed to the hook registered with [`set_drop_panic_hook`].
+///
+/// [`set_drop_panic_hook`]: ./fn.set_drop_panic_hook.html
+#[derive(Debug, Clone, Copy)]
+pub struct DropPanicInfo<'a> {
+    type_name: &'static str,
+    payload: &'a (dyn Any + Send),
+}
+
+impl<'a> DropPanicInfo<'a> {
+    /// The name of the type whose disposal panicked.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str { self.type_name }
+
+    /// The payload of the panic.
+    #[must_use]
+    pub fn payload(&self) -> &'a (dyn Any + Send) { self.payload }
+
+    /// The panic message, if the payload is a string.
+    #[must_use]
+    pub fn message(&self) -> Option<&'a str> {
+        self.payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| self.payload.downcast_ref::<String>().map(String::as_str))
+    }
+}
+
+impl fmt::Display for DropPanicInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "panic while disposing value of type `{}`", self.type_name)?;
+
+        match self.message() {
+            Some(m) => write!(f, ": {m}"),
+            None => Ok(()),
+        }
+    }
+}
+
+static DROP_PANIC_HOOK: Hook<fn(&DropPanicInfo)> = unsafe { Hook::new() };
+
+/// Register a hook to be called when a panic is caught while disposing a
+/// value, replacing any previously-registered hook.
+///
+/// This hook is used by [`DropPanicPolicy::Report`] and by [`CatchPanic`].  If
+/// no hook is registered, [`default_drop_panic_hook`] is used.
+///
+/// [`DropPanicPolicy::Report`]: ./enum.DropPanicPolicy.html#variant.Report
+/// [`CatchPanic`]: ./struct.CatchPanic.html
+/// [`default_drop_panic_hook`]: ./fn.default_drop_panic_hook.html
+pub fn set_drop_panic_hook(hook: fn(&DropPanicInfo)) { DROP_PANIC_HOOK.set(hook); }
+
+/// Unregister the current drop panic hook, returning it.
+///
+/// If no hook was registered, [`default_drop_panic_hook`] is returned.
+///
+/// [`default_drop_panic_hook`]: ./fn.default_drop_panic_hook.html
+pub fn take_drop_panic_hook() -> fn(&DropPanicInfo) {
+    DROP_PANIC_HOOK.take().unwrap_or(default_drop_panic_hook)
+}
+
+/// The default drop panic hook, which prints a message to standard error.
+pub fn default_drop_panic_hook(info: &DropPanicInfo) { eprintln!("{info}"); }
+
+fn report_drop_panic<T: ?Sized>(payload: &(dyn Any + Send)) {
+    let info = DropPanicInfo {
+        type_name: type_name::<T>(),
+        payload,
+    };
+
+    DROP_PANIC_HOOK.get().unwrap_or(default_drop_panic_hook)(&info);
+}
+
+/// Run `f`, which disposes a value of type `T` from inside a `Drop` impl,
+/// applying the global [`DropPanicPolicy`] if the thread is unwinding.
+pub(crate) fn guard_drop_panic<T: ?Sized>(reason: DisposeReason, f: impl FnOnce()) {
+    if reason == DisposeReason::Unwind {
+        let policy = drop_panic_policy();
+
+        if policy != DropPanicPolicy::Propagate {
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(f)) {
+                let info = DropPanicInfo {
+                    type_name: type_name::<T>(),
+                    payload: &*payload,
+                };
+
+                match policy {
+                    DropPanicPolicy::Propagate => unreachable!(),
+                    DropPanicPolicy::Log => eprintln!("{info}"),
+                    DropPanicPolicy::Report => report_drop_panic::<T>(&*payload),
+                    DropPanicPolicy::Abort => {
+                        eprintln!("{info}; aborting");
+                        crate::abort::abort();
+                    },
+                }
+            }
+
+            return;
+        }
+    }
+
+    f();
+}
+
+/// A wrapper that never lets a panic escape from disposing its contents.
+///
+/// Any panic raised while disposing the inner value is caught and passed to
+/// the hook registered with [`set_drop_panic_hook`], regardless of whether
+/// the thread is unwinding or of the global [`DropPanicPolicy`].
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{CatchPanic, Disposable};
+///
+/// {
+///     let _d = Disposable::new(CatchPanic(|| panic!("oh no")));
+/// } // Prints "panic while disposing value of type `...`: oh no"
+/// ```
+///
+/// [`set_drop_panic_hook`]: ./fn.set_drop_panic_hook.html
+/// [`DropPanicPolicy`]: ./enum.DropPanicPolicy.html
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CatchPanic<T>(pub T);
+
+impl<T: Dispose> Dispose for CatchPanic<T> {
+    fn dispose(self) { self.dispose_because(DisposeReason::Explicit) }
+
+    fn dispose_because(self, reason: DisposeReason) {
+        if let Err(payload) = catch_unwind(AssertUnwindSafe(|| self.0.dispose_because(reason))) {
+            report_drop_panic::<T>(&*payload);
+        }
+    }
+}
+
+impl<T> Deref for CatchPanic<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T { &self.0 }
+}
+
+impl<T> DerefMut for CatchPanic<T> {
+    fn deref_mut(&mut self) -> &mut T { &mut self.0 }
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::catch_unwind;
+
+    use super::*;
+    use crate::Disposable;
+
+    struct Panicky;
+
+    impl Dispose for Panicky {
+        fn dispose(self) { panic!("inner"); }
+    }
+
+    #[test]
+    fn policy_ignored_outside_unwind() {
+        let err = catch_unwind(|| drop(Disposable::new(Panicky))).unwrap_err();
+
+        assert_eq!(err.downcast_ref(), Some(&"inner"));
+    }
+
+    #[test]
+    fn catch_panic() { drop(Disposable::new(CatchPanic(Panicky))); }
+}
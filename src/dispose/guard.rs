@@ -0,0 +1,255 @@
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::{forget, ManuallyDrop},
+    ops::{Deref, DerefMut},
+};
+
+/// A policy deciding whether a [`ScopeGuard`] runs its closure when dropped.
+///
+/// [`ScopeGuard`]: ./struct.ScopeGuard.html
+pub trait Strategy {
+    /// Returns true if the guard should run its closure.
+    fn should_run() -> bool;
+}
+
+/// A [`Strategy`] that always runs the guard's closure.
+///
+/// [`Strategy`]: ./trait.Strategy.html
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Always;
+
+impl Strategy for Always {
+    fn should_run() -> bool { true }
+}
+
+/// A [`Strategy`] that only runs the guard's closure if the guard is dropped
+/// while the current thread is unwinding from a panic.
+///
+/// [`Strategy`]: ./trait.Strategy.html
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OnUnwind;
+
+#[cfg(feature = "std")]
+impl Strategy for OnUnwind {
+    fn should_run() -> bool { std::thread::panicking() }
+}
+
+/// A [`Strategy`] that only runs the guard's closure if the guard is dropped
+/// normally, rather than while the current thread is unwinding from a panic.
+///
+/// [`Strategy`]: ./trait.Strategy.html
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OnSuccess;
+
+#[cfg(feature = "std")]
+impl Strategy for OnSuccess {
+    fn should_run() -> bool { !std::thread::panicking() }
+}
+
+/// A guard holding a value, which passes it to a closure at the end of a
+/// lexical scope.
+///
+/// Unlike [`defer_with`], the captured value remains accessible through the
+/// guard (using `Deref` and `DerefMut`) for the rest of the scope.  The
+/// strategy `S` determines whether the closure is actually run when the guard
+/// is dropped; see [`guard_on_unwind`] and [`guard_on_success`].
+///
+/// A guard can be disarmed using [`cancel`] or [`dismiss`], in which case the
+/// closure is never run.
+///
+/// **NOTE:** The unwinding-aware strategies check whether the thread is
+/// panicking at the time the guard is dropped, so a guard created while the
+/// thread is _already_ unwinding (such as inside another `Drop` impl) will
+/// consider any exit to be an unwind.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::guard;
+///
+/// let mut log = guard(Vec::new(), |log| println!("{}", log.join(", ")));
+///
+/// log.push("Hello");
+/// log.push("world!");
+///
+/// drop(log); // Prints "Hello, world!"
+/// ```
+///
+/// [`defer_with`]: ./fn.defer_with.html
+/// [`guard_on_unwind`]: ./fn.guard_on_unwind.html
+/// [`guard_on_success`]: ./fn.guard_on_success.html
+/// [`cancel`]: ./struct.ScopeGuard.html#method.cancel
+/// [`dismiss`]: ./struct.ScopeGuard.html#method.dismiss
+#[must_use = "A guard runs its closure (if at all) as soon as it is dropped."]
+pub struct ScopeGuard<W, F: FnOnce(W), S: Strategy = Always> {
+    val: ManuallyDrop<W>,
+    f: ManuallyDrop<F>,
+    _s: PhantomData<fn() -> S>,
+}
+
+impl<W, F: FnOnce(W), S: Strategy> ScopeGuard<W, F, S> {
+    /// Construct a new guard, passing `val` to `f` when dropped if the
+    /// strategy `S` allows it.
+    #[must_use = "A guard runs its closure (if at all) as soon as it is dropped."]
+    pub fn with_strategy(val: W, f: F) -> Self {
+        Self {
+            val: ManuallyDrop::new(val),
+            f: ManuallyDrop::new(f),
+            _s: PhantomData,
+        }
+    }
+
+    /// Disarm the guard, returning the captured value without running the
+    /// closure.
+    pub fn cancel(mut this: Self) -> W {
+        let val = unsafe { ManuallyDrop::take(&mut this.val) };
+        unsafe { ManuallyDrop::drop(&mut this.f) };
+        forget(this);
+        val
+    }
+
+    /// Disarm the guard, dropping the captured value without running the
+    /// closure.
+    pub fn dismiss(this: Self) { drop(Self::cancel(this)); }
+}
+
+impl<W, F: FnOnce(W), S: Strategy> Drop for ScopeGuard<W, F, S> {
+    fn drop(&mut self) {
+        let val = unsafe { ManuallyDrop::take(&mut self.val) };
+        let f = unsafe { ManuallyDrop::take(&mut self.f) };
+
+        if S::should_run() {
+            f(val);
+        }
+    }
+}
+
+impl<W, F: FnOnce(W), S: Strategy> Deref for ScopeGuard<W, F, S> {
+    type Target = W;
+
+    fn deref(&self) -> &W { &self.val }
+}
+
+impl<W, F: FnOnce(W), S: Strategy> DerefMut for ScopeGuard<W, F, S> {
+    fn deref_mut(&mut self) -> &mut W { &mut self.val }
+}
+
+impl<W: fmt::Debug, F: FnOnce(W), S: Strategy> fmt::Debug for ScopeGuard<W, F, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScopeGuard")
+            .field("val", &*self.val)
+            .field("strategy", &core::any::type_name::<S>())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Construct a guard that passes `val` to `f` at the end of a lexical scope.
+///
+/// See [`ScopeGuard`] for more info.
+///
+/// [`ScopeGuard`]: ./struct.ScopeGuard.html
+#[must_use = "Dropping the guard immediately will run its closure."]
+pub fn guard<W, F: FnOnce(W)>(val: W, f: F) -> ScopeGuard<W, F> {
+    ScopeGuard::with_strategy(val, f)
+}
+
+/// Construct a guard that passes `val` to `f` only if the scope is exited by
+/// unwinding from a panic.
+///
+/// This is useful for rolling back partially-completed work.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::guard_on_unwind;
+///
+/// let mut items = guard_on_unwind(vec![1, 2, 3], |items| {
+///     println!("Rolling back {} items", items.len());
+/// });
+///
+/// items.push(4);
+///
+/// let items = dispose::ScopeGuard::cancel(items); // Success, nothing to roll back
+/// assert_eq!(items, [1, 2, 3, 4]);
+/// ```
+#[cfg(feature = "std")]
+#[must_use = "Dropping the guard immediately will disarm it."]
+pub fn guard_on_unwind<W, F: FnOnce(W)>(val: W, f: F) -> ScopeGuard<W, F, OnUnwind> {
+    ScopeGuard::with_strategy(val, f)
+}
+
+/// Construct a guard that passes `val` to `f` only if the scope is exited
+/// normally, without panicking.
+#[cfg(feature = "std")]
+#[must_use = "Dropping the guard immediately will run its closure."]
+pub fn guard_on_success<W, F: FnOnce(W)>(val: W, f: F) -> ScopeGuard<W, F, OnSuccess> {
+    ScopeGuard::with_strategy(val, f)
+}
+
+/// Defer an action until the end of a lexical scope, running it only if the
+/// scope is exited by unwinding from a panic; similar to [`defer`].
+///
+/// [`defer`]: ./fn.defer.html
+#[cfg(feature = "std")]
+#[must_use = "Dropping the guard immediately will disarm it."]
+pub fn defer_on_unwind<F: FnOnce()>(f: F) -> ScopeGuard<F, fn(F), OnUnwind> {
+    ScopeGuard::with_strategy(f, |f| f())
+}
+
+/// Defer an action until the end of a lexical scope, running it only if the
+/// scope is exited normally, without panicking; similar to [`defer`].
+///
+/// [`defer`]: ./fn.defer.html
+#[cfg(feature = "std")]
+#[must_use = "Dropping the guard immediately will run its closure."]
+pub fn defer_on_success<F: FnOnce()>(f: F) -> ScopeGuard<F, fn(F), OnSuccess> {
+    ScopeGuard::with_strategy(f, |f| f())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use std::{
+        cell::RefCell,
+        panic::{catch_unwind, AssertUnwindSafe},
+        vec::Vec,
+    };
+
+    use super::*;
+
+    fn run(log: &RefCell<Vec<&'static str>>, fail: bool) {
+        let _a = defer_on_unwind(|| log.borrow_mut().push("unwind"));
+        let _b = defer_on_success(|| log.borrow_mut().push("success"));
+        let _c = guard("always", |s| log.borrow_mut().push(s));
+
+        assert!(!fail, "oh no");
+    }
+
+    #[test]
+    fn strategies() {
+        let log = RefCell::new(Vec::new());
+
+        run(&log, false);
+        assert_eq!(*log.borrow(), ["always", "success"]);
+
+        log.borrow_mut().clear();
+        catch_unwind(AssertUnwindSafe(|| run(&log, true))).unwrap_err();
+        assert_eq!(*log.borrow(), ["always", "unwind"]);
+    }
+
+    #[test]
+    fn cancel() {
+        let log = RefCell::new(Vec::new());
+
+        let mut g = guard(1, |i| log.borrow_mut().push(i));
+        *g += 1;
+        assert_eq!(ScopeGuard::cancel(g), 2);
+
+        drop(guard(3, |i| log.borrow_mut().push(i)));
+        ScopeGuard::dismiss(guard(4, |i| log.borrow_mut().push(i)));
+
+        assert_eq!(*log.borrow(), [3]);
+    }
+}
@@ -0,0 +1,47 @@
+use core::{
+    marker::PhantomData,
+    mem::{size_of, transmute_copy},
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// A global, lock-free slot holding an optional function pointer.
+///
+/// This is used to store the various user-configurable hooks exposed by this
+/// crate without requiring a lock.
+pub(crate) struct Hook<F: Copy>(AtomicPtr<()>, PhantomData<F>);
+
+impl<F: Copy> Hook<F> {
+    /// Construct a new, empty hook slot.
+    ///
+    /// # Safety
+    /// `F` must be a function pointer type.
+    pub(crate) const unsafe fn new() -> Self {
+        const { assert!(size_of::<F>() == size_of::<*mut ()>()) };
+
+        Self(AtomicPtr::new(ptr::null_mut()), PhantomData)
+    }
+
+    fn from_ptr(ptr: *mut ()) -> Option<F> {
+        // SAFETY: the only non-null values stored in this slot are valid
+        //         function pointers of type F, as guaranteed by the caller of
+        //         new()
+        (!ptr.is_null()).then(|| unsafe { transmute_copy::<*mut (), F>(&ptr) })
+    }
+
+    /// Retrieve the currently-registered function, if any.
+    pub(crate) fn get(&self) -> Option<F> { Self::from_ptr(self.0.load(Ordering::Acquire)) }
+
+    /// Register a new function, returning the previous one, if any.
+    pub(crate) fn set(&self, f: F) -> Option<F> {
+        // SAFETY: F is a function pointer, which is the same size as *mut ()
+        let ptr = unsafe { transmute_copy::<F, *mut ()>(&f) };
+
+        Self::from_ptr(self.0.swap(ptr, Ordering::AcqRel))
+    }
+
+    /// Unregister the current function, returning it if one was registered.
+    pub(crate) fn take(&self) -> Option<F> {
+        Self::from_ptr(self.0.swap(ptr::null_mut(), Ordering::AcqRel))
+    }
+}
@@ -0,0 +1,318 @@
+//! Live-resource accounting for [`Disposable`] values.
+//!
+//! With the `leak-check` feature enabled, every [`Disposable`] registers
+//! itself in a global table when constructed and removes itself when disposed.
+//! Values that escape through `mem::forget`, reference cycles, or
+//! [`Disposable::leak`] therefore remain in the table, and can be inspected
+//! using the functions in this module.  Every call to `Disposable::leak` is
+//! also recorded separately.
+//!
+//! By default, a backtrace of the site where each value was created is
+//! captured according to the `RUST_BACKTRACE` and `RUST_LIB_BACKTRACE`
+//! environment variables, as with [`Backtrace::capture`].  Use
+//! [`set_capture_backtraces`] to always capture them instead.
+//!
+//! # Examples
+//!
+//! ```
+//! use dispose::{leak_check, Disposable};
+//!
+//! leak_check::assert_no_leaks(|| {
+//!     let _d = Disposable::new(|| println!("Goodbye!"));
+//! });
+//! ```
+//!
+//! ```should_panic
+//! use dispose::{leak_check, Disposable};
+//!
+//! leak_check::assert_no_leaks(|| {
+//!     std::mem::forget(Disposable::new(|| println!("Goodbye!")));
+//! });
+//! ```
+//!
+//! [`Disposable`]: ../struct.Disposable.html
+//! [`Disposable::leak`]: ../struct.Disposable.html#method.leak
+//! [`Backtrace::capture`]: std::backtrace::Backtrace::capture
+//! [`set_capture_backtraces`]: ./fn.set_capture_backtraces.html
+
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    collections::BTreeMap,
+    fmt, io,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, ThreadId},
+};
+
+/// A record of a single [`Disposable`] value, either still alive or leaked.
+///
+/// [`Disposable`]: ../struct.Disposable.html
+#[derive(Debug, Clone)]
+pub struct ResourceRecord {
+    id: u64,
+    type_name: &'static str,
+    thread: ThreadId,
+    backtrace: Arc<Backtrace>,
+}
+
+impl ResourceRecord {
+    fn capture(id: u64, type_name: &'static str) -> Self {
+        let backtrace = if CAPTURE_BACKTRACES.load(Ordering::Relaxed) {
+            Backtrace::force_capture()
+        } else {
+            Backtrace::capture()
+        };
+
+        Self {
+            id,
+            type_name,
+            thread: thread::current().id(),
+            backtrace: Arc::new(backtrace),
+        }
+    }
+
+    /// A unique, increasing identifier for the value.
+    #[must_use]
+    pub fn id(&self) -> u64 { self.id }
+
+    /// The name of the type of the value.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str { self.type_name }
+
+    /// The thread the record was created on.
+    #[must_use]
+    pub fn thread(&self) -> ThreadId { self.thread }
+
+    /// The backtrace captured when the record was created.  This will be empty
+    /// if backtraces were not enabled at the time.
+    pub fn backtrace(&self) -> &Backtrace { &self.backtrace }
+}
+
+impl fmt::Display for ResourceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} `{}` ({:?})", self.id, self.type_name, self.thread)?;
+
+        if self.backtrace.status() == BacktraceStatus::Captured {
+            write!(f, "\n{}", self.backtrace)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Registry {
+    live: BTreeMap<u64, ResourceRecord>,
+    leaks: Vec<ResourceRecord>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    live: BTreeMap::new(),
+    leaks: Vec::new(),
+});
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static CAPTURE_BACKTRACES: AtomicBool = AtomicBool::new(false);
+
+fn registry() -> MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A registration in the live-resource table, held by each `Disposable`.
+#[derive(Debug)]
+pub(crate) struct Tracker(u64);
+
+impl Tracker {
+    /// Register a new value of type `T`.
+    pub(crate) fn new<T: ?Sized>() -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let rec = ResourceRecord::capture(id, core::any::type_name::<T>());
+
+        registry().live.insert(id, rec);
+        Self(id)
+    }
+
+    /// Remove the value from the table, as it has been disposed or otherwise
+    /// intentionally released.
+    pub(crate) fn release(&self) { registry().live.remove(&self.0); }
+
+    /// Remove the value from the table, and record that it was leaked.
+    pub(crate) fn leak(&self) {
+        let mut reg = registry();
+
+        if let Some(rec) = reg.live.remove(&self.0) {
+            let leak = ResourceRecord::capture(rec.id, rec.type_name);
+            reg.leaks.push(leak);
+        }
+    }
+}
+
+/// Set whether backtraces should always be captured when a value is created
+/// or leaked, regardless of environment variables.
+pub fn set_capture_backtraces(capture: bool) {
+    CAPTURE_BACKTRACES.store(capture, Ordering::Relaxed);
+}
+
+/// Returns a snapshot of every value that is still alive, in order of
+/// creation.
+#[must_use]
+pub fn live_resources() -> Vec<ResourceRecord> { registry().live.values().cloned().collect() }
+
+/// Returns the number of values still alive, grouped by type name.
+#[must_use]
+pub fn live_counts() -> BTreeMap<&'static str, usize> {
+    let reg = registry();
+    let mut counts = BTreeMap::new();
+
+    for rec in reg.live.values() {
+        *counts.entry(rec.type_name).or_default() += 1;
+    }
+
+    counts
+}
+
+/// Returns a record of every call to [`Disposable::leak`].  The backtrace of
+/// each record is that of the call to `leak`.
+///
+/// [`Disposable::leak`]: ../struct.Disposable.html#method.leak
+#[must_use]
+pub fn leak_records() -> Vec<ResourceRecord> { registry().leaks.clone() }
+
+/// Discard all records produced by [`Disposable::leak`].
+///
+/// [`Disposable::leak`]: ../struct.Disposable.html#method.leak
+pub fn clear_leak_records() { registry().leaks.clear(); }
+
+/// Write a human-readable list of every value still alive, followed by every
+/// call to [`Disposable::leak`], to `out`.
+///
+/// # Errors
+/// This function returns an error if writing to `out` fails.
+///
+/// [`Disposable::leak`]: ../struct.Disposable.html#method.leak
+pub fn dump_resources(mut out: impl io::Write) -> io::Result<()> {
+    let (live, leaks) = {
+        let reg = registry();
+        (reg.live.values().cloned().collect::<Vec<_>>(), reg.leaks.clone())
+    };
+
+    writeln!(out, "{} live resource(s):", live.len())?;
+    for rec in &live {
+        writeln!(out, "  {rec}")?;
+    }
+
+    writeln!(out, "{} leaked resource(s):", leaks.len())?;
+    for rec in &leaks {
+        writeln!(out, "  {rec}")?;
+    }
+
+    Ok(())
+}
+
+fn panic_with(what: &str, recs: &[ResourceRecord]) -> ! {
+    use fmt::Write;
+
+    let mut msg = format!("{} {what}:", recs.len());
+    for rec in recs {
+        write!(msg, "\n  {rec}").unwrap();
+    }
+
+    panic!("{msg}");
+}
+
+/// Assert that no values are alive in the entire process.
+///
+/// # Panics
+/// This function panics, listing the outstanding values, if any are alive.
+pub fn assert_no_live_resources() {
+    let live = live_resources();
+
+    if !live.is_empty() {
+        panic_with("resource(s) still alive", &live);
+    }
+}
+
+/// Run `f`, then assert that every value it created on the current thread has
+/// been disposed.
+///
+/// Unlike [`assert_no_live_resources`], this ignores values created by other
+/// threads, making it suitable for use in tests run in parallel.
+///
+/// # Panics
+/// This function panics, listing the outstanding values, if any values created
+/// by `f` on the current thread are still alive.
+///
+/// [`assert_no_live_resources`]: ./fn.assert_no_live_resources.html
+pub fn assert_no_leaks<R>(f: impl FnOnce() -> R) -> R {
+    let start = NEXT_ID.load(Ordering::Relaxed);
+    let thread = thread::current().id();
+
+    let ret = f();
+
+    let live: Vec<_> = registry()
+        .live
+        .range(start..)
+        .map(|(_, r)| r)
+        .filter(|r| r.thread == thread)
+        .cloned()
+        .collect();
+
+    if !live.is_empty() {
+        panic_with("resource(s) leaked", &live);
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, mem, panic::catch_unwind};
+
+    use super::*;
+    use crate::{Disposable, Dispose, LocalSharedDisposable};
+
+    #[test]
+    fn disposed_values_untracked() {
+        assert_no_leaks(|| {
+            let a = Disposable::new(|| ());
+            let b = Disposable::new(|| ());
+
+            drop(a);
+            Disposable::try_dispose(b).unwrap();
+        });
+    }
+
+    #[test]
+    fn forgotten_values_tracked() {
+        let thread = thread::current().id();
+        let d = Disposable::new(|| ());
+        let f = Disposable::new(|| ());
+        mem::forget(f);
+
+        let mine = |v: Vec<ResourceRecord>| v.into_iter().filter(|r| r.thread == thread).count();
+        assert_eq!(mine(live_resources()), 2);
+
+        let _ = unsafe { Disposable::leak(d) };
+        assert_eq!(mine(live_resources()), 1);
+        assert_eq!(mine(leak_records()), 1);
+    }
+
+    #[test]
+    fn cycles_tracked() {
+        struct Node(RefCell<Option<LocalSharedDisposable<Node>>>);
+
+        impl Dispose for Node {
+            fn dispose(self) {}
+        }
+
+        let res = catch_unwind(|| {
+            assert_no_leaks(|| {
+                let a = LocalSharedDisposable::new(Node(RefCell::new(None)));
+                a.0.replace(Some(a.clone()));
+            });
+        });
+
+        assert!(res.is_err());
+    }
+}
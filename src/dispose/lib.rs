@@ -9,6 +9,7 @@
 )]
 #![warn(clippy::pedantic, missing_docs)]
 #![allow(clippy::module_name_repetitions)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
 //! A small crate for handling resources that must be consumed at the end of
 //! their lifetime.
@@ -22,7 +23,15 @@
 //!
 //! As a bonus, this crate makes it easy to defer the execution of an `FnOnce`
 //! closure to the end of a scope, which can be done using the [`defer`]
-//! function.
+//! function.  The [`ScopeGuard`] type extends this to actions that should only
+//! run when a scope exits normally, or only when it unwinds.  When the number
+//! of resources in a scope is not known ahead of time, they can be collected
+//! in a [`DisposeStack`] instead.
+//!
+//! For resources whose teardown can fail, the [`TryDispose`] trait provides a
+//! fallible alternative to `Dispose` that can also be used with `Disposable`,
+//! and resources that must be torn down asynchronously can implement
+//! [`AsyncDispose`] and be wrapped in an [`AsyncDisposable`].
 //!
 //! **NOTE:** The `Dispose` trait does _not_ provide a `Drop` impl by itself.
 //! For that, a value implementing `Dispose` must be wrapped in a [`Disposable`]
@@ -85,20 +94,104 @@
 //! cryptographically secure. Please do not clean up secure memory by simply
 //! setting it to zero.)
 //!
+//! # Features
+//!
+//! This crate supports `no_std` environments.  The following cargo features
+//! control which parts of it are available:
+//!
+//! - `std` (enabled by default) enables catching panics during disposal (see
+//!   [`DisposeIterator`] and [`DropPanicPolicy`]), scope guards that detect
+//!   unwinding, writing errors to standard error, ambient dispose contexts (see
+//!   [`with_dispose_context`]), and blocking on asynchronous disposal using a
+//!   thread-parking executor.  Without it,
+//!   [`AbortCanary`] calls the handler registered with [`set_abort_handler`]
+//!   rather than `std::process::abort`.
+//! - `alloc` (implied by `std`) enables the implementations for `Vec` and
+//!   `Box<[T]>`, as well as [`DisposeErrors`] and everything that depends on it,
+//!   such as the `TryDispose` derive macro.  [`DisposeStack`],
+//!   [`DisposeScope`], [`DisposeDyn`], and [`SharedDisposable`] also require
+//!   it; [`InlineDisposeStack`] does not.
+//! - `leak-check` (implies `std`) tracks every live [`Disposable`] and every
+//!   call to [`leak`], which can be inspected using the [`leak_check`] module.
+//!   This adds overhead to every `Disposable`, and is intended for debugging
+//!   and testing.
+//! - `testing` (implies `alloc`) enables the [`testing`] module, which contains
+//!   helpers for checking the order in which values are disposed.
+//!
 //! [`defer`]: ./fn.defer.html
+//! [`ScopeGuard`]: ./struct.ScopeGuard.html
+//! [`DisposeStack`]: ./struct.DisposeStack.html
+//! [`DisposeIterator`]: ./trait.DisposeIterator.html
+//! [`DropPanicPolicy`]: ./enum.DropPanicPolicy.html
+//! [`with_dispose_context`]: ./fn.with_dispose_context.html
+//! [`AbortCanary`]: ./struct.AbortCanary.html
+//! [`set_abort_handler`]: ./fn.set_abort_handler.html
+//! [`DisposeErrors`]: ./struct.DisposeErrors.html
+//! [`DisposeScope`]: ./struct.DisposeScope.html
+//! [`DisposeDyn`]: ./trait.DisposeDyn.html
+//! [`SharedDisposable`]: ./struct.SharedDisposable.html
+//! [`InlineDisposeStack`]: ./struct.InlineDisposeStack.html
 //! [`Disposable`]: ./struct.Disposable.html
 //! [`leak`]: ./struct.Disposable.html#method.leak
+//! [`leak_check`]: ./leak_check/index.html
+//! [`testing`]: ./testing/index.html
 //! [`Dispose`]: ./derive.Dispose.html
+//! [`TryDispose`]: ./trait.TryDispose.html
+//! [`AsyncDispose`]: ./trait.AsyncDispose.html
+//! [`AsyncDisposable`]: ./struct.AsyncDisposable.html
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 mod abort;
+mod async_dispose;
+#[cfg(feature = "std")]
+mod context;
 mod defer;
 mod disposable;
+mod disposable_with;
 mod dispose;
+#[cfg(feature = "alloc")]
+mod dispose_dyn;
+#[cfg(feature = "alloc")]
+mod dispose_errors;
+#[cfg(feature = "alloc")]
+mod dispose_scope;
+mod dispose_stack;
 mod dispose_with;
+#[cfg(feature = "std")]
+mod drop_panic;
+mod guard;
+mod hook;
+#[cfg(feature = "leak-check")]
+pub mod leak_check;
+#[cfg(feature = "alloc")]
+mod shared;
+#[cfg(any(feature = "testing", all(test, feature = "alloc")))]
+pub mod testing;
+mod try_dispose;
+mod unwind;
 
 pub use dispose_derive::*;
 
-pub use crate::{abort::*, defer::*, disposable::*, dispose::*, dispose_with::*};
+#[cfg(feature = "alloc")]
+pub use crate::{dispose_dyn::*, dispose_errors::*, dispose_scope::*, shared::*};
+pub use crate::{
+    abort::*, async_dispose::*, defer::*, disposable::*, disposable_with::*, dispose::*,
+    dispose_stack::*, dispose_with::*, guard::*, try_dispose::*,
+};
+#[cfg(feature = "std")]
+pub use crate::{
+    context::{dispose_context, with_dispose_context, MissingContext},
+    drop_panic::*,
+};
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "std")]
+    pub use crate::context::{box_error, expect_context, try_context};
+    pub use crate::unwind::{CatchUnwind, Panics};
+}
 
 /// Contains all the basic traits and derive macros exported by this crate.
 pub mod prelude {
@@ -106,5 +199,5 @@ pub mod prelude {
     pub use dispose_derive::*;
 
     #[doc(no_inline)]
-    pub use super::{Dispose as _, DisposeWith};
+    pub use super::{Dispose as _, DisposeWith, TryDispose, TryDisposeWith};
 }
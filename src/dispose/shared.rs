@@ -0,0 +1,360 @@
+use alloc::{
+    rc::{self, Rc},
+    sync::{self, Arc},
+};
+use core::{borrow::Borrow, ops::Deref};
+
+use crate::{Disposable, Dispose, DisposeReason, TryDispose};
+
+/// A thread-safe, reference-counted [`Disposable`].
+///
+/// Cloning a `SharedDisposable` produces another handle to the same value.
+/// Dropping a handle only decrements the reference count, except for the last
+/// strong handle, which disposes the contained value.  Values implementing
+/// [`TryDispose`] can also be shared; any errors produced are passed to the
+/// hook registered with [`set_drop_error_hook`], exactly as with
+/// `Disposable`.
+///
+/// If `T` implements [`Dispose`], so does `SharedDisposable<T>`, allowing
+/// handles to be held in containers such as those deriving `Dispose`.
+/// Disposing a handle behaves exactly like dropping it: only disposing the
+/// last strong handle disposes the contained value, passing it the same
+/// [`DisposeReason`].
+///
+/// Non-owning handles can be created with [`downgrade`], and do not keep the
+/// value alive.
+///
+/// # Concurrency
+///
+/// When several threads drop their handles at the same time, exactly one of
+/// them disposes the value: the thread releasing the last strong reference.
+/// Disposal happens on that thread, before its call to `drop` returns.  A
+/// [`WeakDisposable::upgrade`] racing with the final drop either fails, or
+/// succeeds and produces a new strong handle that becomes responsible for
+/// disposing the value.  The value is never disposed more than once, and is
+/// never disposed while any strong handle to it is still alive.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{Dispose, SharedDisposable};
+///
+/// struct Device;
+///
+/// impl Dispose for Device {
+///     fn dispose(self) { println!("Destroying device"); }
+/// }
+///
+/// let dev = SharedDisposable::new(Device);
+/// let dev2 = dev.clone();
+///
+/// std::thread::spawn(move || drop(dev2)).join().unwrap();
+/// drop(dev); // Prints "Destroying device"
+/// ```
+///
+/// [`Disposable`]: ./struct.Disposable.html
+/// [`TryDispose`]: ./trait.TryDispose.html
+/// [`set_drop_error_hook`]: ./fn.set_drop_error_hook.html
+/// [`Dispose`]: ./trait.Dispose.html
+/// [`DisposeReason`]: ./enum.DisposeReason.html
+/// [`downgrade`]: ./struct.SharedDisposable.html#method.downgrade
+/// [`WeakDisposable::upgrade`]: ./struct.WeakDisposable.html#method.upgrade
+#[derive(Debug)]
+pub struct SharedDisposable<T: TryDispose>(Arc<Disposable<T>>);
+
+impl<T: TryDispose> SharedDisposable<T> {
+    /// Construct a new `SharedDisposable`, wrapping around `val`.
+    pub fn new(val: T) -> Self { Self(Arc::new(Disposable::new(val))) }
+
+    /// Consume the handle, producing the contained value if this was the last
+    /// strong handle to it.
+    ///
+    /// The value is returned in a [`Disposable`], allowing the final owner to
+    /// dispose it explicitly (for instance, to inspect any error returned by
+    /// [`Disposable::try_dispose`]).
+    ///
+    /// [`Disposable`]: ./struct.Disposable.html
+    /// [`Disposable::try_dispose`]: ./struct.Disposable.html#method.try_dispose
+    #[must_use]
+    pub fn into_inner(this: Self) -> Option<Disposable<T>> { Arc::into_inner(this.0) }
+
+    /// Create a new non-owning handle to the contained value.
+    #[must_use]
+    pub fn downgrade(this: &Self) -> WeakDisposable<T> { WeakDisposable(Arc::downgrade(&this.0)) }
+
+    /// Returns the number of strong handles to the contained value.
+    #[must_use]
+    pub fn strong_count(this: &Self) -> usize { Arc::strong_count(&this.0) }
+
+    /// Returns true if both handles point to the same value.
+    #[must_use]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool { Arc::ptr_eq(&this.0, &other.0) }
+}
+
+impl<T: TryDispose> Clone for SharedDisposable<T> {
+    fn clone(&self) -> Self { Self(Arc::clone(&self.0)) }
+}
+
+impl<T: Dispose> Dispose for SharedDisposable<T> {
+    /// Release this handle, disposing the contained value if this was the last
+    /// strong handle to it.
+    fn dispose(self) { self.dispose_because(DisposeReason::Explicit) }
+
+    fn dispose_because(self, reason: DisposeReason) {
+        if let Some(inner) = Arc::into_inner(self.0) {
+            // SAFETY: the value is disposed immediately, so there is no leak
+            //         to record
+            unsafe { Disposable::take(inner) }.dispose_because(reason);
+        }
+    }
+}
+
+impl<T: TryDispose> From<T> for SharedDisposable<T> {
+    fn from(val: T) -> Self { Self::new(val) }
+}
+
+impl<T: TryDispose> From<Disposable<T>> for SharedDisposable<T> {
+    fn from(val: Disposable<T>) -> Self { Self(Arc::new(val)) }
+}
+
+impl<T: TryDispose> AsRef<T> for SharedDisposable<T> {
+    fn as_ref(&self) -> &T { &self.0 }
+}
+
+impl<T: TryDispose> Borrow<T> for SharedDisposable<T> {
+    fn borrow(&self) -> &T { self.as_ref() }
+}
+
+impl<T: TryDispose> Deref for SharedDisposable<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T { self.as_ref() }
+}
+
+/// A non-owning handle to the value held by a [`SharedDisposable`].
+///
+/// [`SharedDisposable`]: ./struct.SharedDisposable.html
+#[derive(Debug)]
+pub struct WeakDisposable<T: TryDispose>(sync::Weak<Disposable<T>>);
+
+impl<T: TryDispose> WeakDisposable<T> {
+    /// Attempt to create a new strong handle to the value, returning `None` if
+    /// it has already been disposed (or is being disposed).
+    #[must_use]
+    pub fn upgrade(&self) -> Option<SharedDisposable<T>> {
+        self.0.upgrade().map(SharedDisposable)
+    }
+
+    /// Returns the number of strong handles to the value.
+    #[must_use]
+    pub fn strong_count(&self) -> usize { self.0.strong_count() }
+}
+
+impl<T: TryDispose> Clone for WeakDisposable<T> {
+    fn clone(&self) -> Self { Self(sync::Weak::clone(&self.0)) }
+}
+
+/// A single-threaded, reference-counted [`Disposable`].
+///
+/// This is the `Rc`-based counterpart to [`SharedDisposable`]; the last strong
+/// handle to be dropped or disposed disposes the contained value.
+///
+/// [`Disposable`]: ./struct.Disposable.html
+/// [`SharedDisposable`]: ./struct.SharedDisposable.html
+#[derive(Debug)]
+pub struct LocalSharedDisposable<T: TryDispose>(Rc<Disposable<T>>);
+
+impl<T: TryDispose> LocalSharedDisposable<T> {
+    /// Construct a new `LocalSharedDisposable`, wrapping around `val`.
+    pub fn new(val: T) -> Self { Self(Rc::new(Disposable::new(val))) }
+
+    /// Consume the handle, producing the contained value if this was the last
+    /// strong handle to it.
+    ///
+    /// See [`SharedDisposable::into_inner`].
+    ///
+    /// [`SharedDisposable::into_inner`]: ./struct.SharedDisposable.html#method.into_inner
+    #[must_use]
+    pub fn into_inner(this: Self) -> Option<Disposable<T>> { Rc::into_inner(this.0) }
+
+    /// Create a new non-owning handle to the contained value.
+    #[must_use]
+    pub fn downgrade(this: &Self) -> LocalWeakDisposable<T> {
+        LocalWeakDisposable(Rc::downgrade(&this.0))
+    }
+
+    /// Returns the number of strong handles to the contained value.
+    #[must_use]
+    pub fn strong_count(this: &Self) -> usize { Rc::strong_count(&this.0) }
+
+    /// Returns true if both handles point to the same value.
+    #[must_use]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool { Rc::ptr_eq(&this.0, &other.0) }
+}
+
+impl<T: TryDispose> Clone for LocalSharedDisposable<T> {
+    fn clone(&self) -> Self { Self(Rc::clone(&self.0)) }
+}
+
+impl<T: Dispose> Dispose for LocalSharedDisposable<T> {
+    /// Release this handle, disposing the contained value if this was the last
+    /// strong handle to it.
+    fn dispose(self) { self.dispose_because(DisposeReason::Explicit) }
+
+    fn dispose_because(self, reason: DisposeReason) {
+        if let Some(inner) = Rc::into_inner(self.0) {
+            // SAFETY: the value is disposed immediately, so there is no leak
+            //         to record
+            unsafe { Disposable::take(inner) }.dispose_because(reason);
+        }
+    }
+}
+
+impl<T: TryDispose> From<T> for LocalSharedDisposable<T> {
+    fn from(val: T) -> Self { Self::new(val) }
+}
+
+impl<T: TryDispose> From<Disposable<T>> for LocalSharedDisposable<T> {
+    fn from(val: Disposable<T>) -> Self { Self(Rc::new(val)) }
+}
+
+impl<T: TryDispose> AsRef<T> for LocalSharedDisposable<T> {
+    fn as_ref(&self) -> &T { &self.0 }
+}
+
+impl<T: TryDispose> Borrow<T> for LocalSharedDisposable<T> {
+    fn borrow(&self) -> &T { self.as_ref() }
+}
+
+impl<T: TryDispose> Deref for LocalSharedDisposable<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T { self.as_ref() }
+}
+
+/// A non-owning handle to the value held by a [`LocalSharedDisposable`].
+///
+/// [`LocalSharedDisposable`]: ./struct.LocalSharedDisposable.html
+#[derive(Debug)]
+pub struct LocalWeakDisposable<T: TryDispose>(rc::Weak<Disposable<T>>);
+
+impl<T: TryDispose> LocalWeakDisposable<T> {
+    /// Attempt to create a new strong handle to the value, returning `None` if
+    /// it has already been disposed (or is being disposed).
+    #[must_use]
+    pub fn upgrade(&self) -> Option<LocalSharedDisposable<T>> {
+        self.0.upgrade().map(LocalSharedDisposable)
+    }
+
+    /// Returns the number of strong handles to the value.
+    #[must_use]
+    pub fn strong_count(&self) -> usize { self.0.strong_count() }
+}
+
+impl<T: TryDispose> Clone for LocalWeakDisposable<T> {
+    fn clone(&self) -> Self { Self(rc::Weak::clone(&self.0)) }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Barrier,
+        },
+        thread,
+        vec::Vec,
+    };
+
+    use super::*;
+
+    struct Counter<'a>(&'a AtomicUsize);
+
+    impl Dispose for Counter<'_> {
+        fn dispose(self) { self.0.fetch_add(1, Ordering::SeqCst); }
+    }
+
+    #[test]
+    fn last_handle_disposes() {
+        let count = AtomicUsize::new(0);
+
+        let a = LocalSharedDisposable::new(Counter(&count));
+        let b = a.clone();
+        let weak = LocalSharedDisposable::downgrade(&a);
+
+        drop(a);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        assert!(weak.upgrade().is_some());
+
+        drop(b);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn dispose_releases_handle() {
+        let count = AtomicUsize::new(0);
+
+        let a = SharedDisposable::new(Counter(&count));
+        let b = a.clone();
+        let weak = SharedDisposable::downgrade(&a);
+
+        a.dispose();
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        assert_eq!(weak.strong_count(), 1);
+
+        b.dispose();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(weak.upgrade().is_none());
+
+        let a = LocalSharedDisposable::new(Counter(&count));
+        let b = a.clone();
+
+        a.dispose();
+        assert_eq!(LocalSharedDisposable::strong_count(&b), 1);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        b.dispose();
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn into_inner() {
+        let count = AtomicUsize::new(0);
+
+        let a = SharedDisposable::new(Counter(&count));
+        let b = a.clone();
+
+        assert!(SharedDisposable::into_inner(a).is_none());
+        let inner = SharedDisposable::into_inner(b).unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        drop(inner);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn concurrent_drops() {
+        const THREADS: usize = 8;
+
+        for _ in 0..64 {
+            let count = AtomicUsize::new(0);
+            let barrier = Barrier::new(THREADS);
+            let shared = SharedDisposable::new(Counter(&count));
+            let handles: Vec<_> = (0..THREADS).map(|_| shared.clone()).collect();
+            drop(shared);
+
+            thread::scope(|s| {
+                for h in handles {
+                    let barrier = &barrier;
+                    s.spawn(move || {
+                        barrier.wait();
+                        drop(h);
+                    });
+                }
+            });
+
+            assert_eq!(count.load(Ordering::SeqCst), 1);
+        }
+    }
+}
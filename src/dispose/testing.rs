@@ -0,0 +1,295 @@
+//! Helpers for testing disposal logic.
+//!
+//! A [`Recorder`] hands out [`Probe`] values, which implement [`Dispose`],
+//! [`DisposeWith`], [`AsyncDispose`], and [`AsyncDisposeWith`] and log their
+//! label to the recorder when disposed.  Probes can be placed in the fields of
+//! a derived type, wrapped in a [`Disposable`], or passed to [`defer`] (via
+//! [`Recorder::callback`]), and the recorder can then be used to assert the
+//! order in which they were disposed.
+//!
+//! With the `std` feature enabled, this module also provides
+//! [`run_in_subprocess`] and [`assert_aborts`], for testing code that is
+//! expected to abort the process.
+//!
+//! This module requires the `testing` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use dispose::{defer, testing::Recorder, Disposable};
+//!
+//! let rec = Recorder::new();
+//!
+//! {
+//!     let _a = Disposable::new(rec.probe("a"));
+//!     let _b = defer(rec.callback("b"));
+//! }
+//!
+//! rec.assert_order(&["b", "a"]);
+//! rec.assert_disposed_once("a");
+//! ```
+//!
+//! [`Recorder`]: ./struct.Recorder.html
+//! [`Probe`]: ./struct.Probe.html
+//! [`Dispose`]: ../trait.Dispose.html
+//! [`DisposeWith`]: ../trait.DisposeWith.html
+//! [`AsyncDispose`]: ../trait.AsyncDispose.html
+//! [`AsyncDisposeWith`]: ../trait.AsyncDisposeWith.html
+//! [`Disposable`]: ../struct.Disposable.html
+//! [`defer`]: ../fn.defer.html
+//! [`Recorder::callback`]: ./struct.Recorder.html#method.callback
+//! [`run_in_subprocess`]: ./fn.run_in_subprocess.html
+//! [`assert_aborts`]: ./fn.assert_aborts.html
+
+use alloc::{
+    borrow::Cow,
+    collections::BTreeSet,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{any::type_name, cell::RefCell};
+
+#[cfg(feature = "std")]
+pub use self::subprocess::*;
+use crate::{AsyncDispose, AsyncDisposeWith, Dispose, DisposeWith};
+
+#[cfg(feature = "std")]
+mod subprocess;
+
+/// A single disposal logged by a [`Recorder`].
+///
+/// [`Recorder`]: ./struct.Recorder.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    label: String,
+    with: Option<&'static str>,
+}
+
+impl Event {
+    /// The label of the probe that was disposed.
+    #[must_use]
+    pub fn label(&self) -> &str { &self.label }
+
+    /// The name of the type of the value passed to `dispose_with`, if the
+    /// probe was disposed using [`DisposeWith`] or [`AsyncDisposeWith`].
+    ///
+    /// [`DisposeWith`]: ../trait.DisposeWith.html
+    /// [`AsyncDisposeWith`]: ../trait.AsyncDisposeWith.html
+    #[must_use]
+    pub fn with(&self) -> Option<&'static str> { self.with }
+}
+
+#[derive(Debug, Default)]
+struct RecorderInner {
+    events: Vec<Event>,
+    created: Vec<String>,
+    panic_on: BTreeSet<String>,
+}
+
+/// A shared log of disposal events, produced by [`Probe`] values.
+///
+/// Cloning a recorder produces another handle to the same log.
+///
+/// [`Probe`]: ./struct.Probe.html
+#[derive(Debug, Clone, Default)]
+pub struct Recorder(Rc<RefCell<RecorderInner>>);
+
+impl Recorder {
+    /// Construct a new, empty recorder.
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
+
+    /// Create a new probe that logs `label` to this recorder when disposed.
+    #[must_use]
+    pub fn probe(&self, label: impl Into<Cow<'static, str>>) -> Probe {
+        let label = label.into();
+        self.0.borrow_mut().created.push(label.to_string());
+
+        Probe {
+            label,
+            rec: self.clone(),
+        }
+    }
+
+    /// Create a probe for each label in `labels`.
+    pub fn probes<L: Into<Cow<'static, str>>>(
+        &self,
+        labels: impl IntoIterator<Item = L>,
+    ) -> Vec<Probe> {
+        labels.into_iter().map(|l| self.probe(l)).collect()
+    }
+
+    /// Create a closure that logs `label` to this recorder when called, for
+    /// use with [`defer`].
+    ///
+    /// [`defer`]: ../fn.defer.html
+    pub fn callback(&self, label: impl Into<Cow<'static, str>>) -> impl FnOnce() {
+        let probe = self.probe(label);
+        move || Dispose::dispose(probe)
+    }
+
+    /// Cause every probe labeled `label` to panic after logging its disposal.
+    pub fn panic_on(&self, label: impl Into<Cow<'static, str>>) {
+        self.0.borrow_mut().panic_on.insert(label.into().into_owned());
+    }
+
+    /// Returns every event logged so far, in order.
+    #[must_use]
+    pub fn events(&self) -> Vec<Event> { self.0.borrow().events.clone() }
+
+    /// Returns the labels of every probe disposed so far, in order.
+    #[must_use]
+    pub fn labels(&self) -> Vec<String> {
+        self.0.borrow().events.iter().map(|e| e.label.clone()).collect()
+    }
+
+    /// Discard all logged events.
+    pub fn clear(&self) { self.0.borrow_mut().events.clear(); }
+
+    /// Assert that the probes disposed so far were disposed in exactly the
+    /// order given.
+    ///
+    /// # Panics
+    /// This function panics if the logged labels do not match `expected`.
+    #[track_caller]
+    pub fn assert_order(&self, expected: &[&str]) {
+        let labels = self.labels();
+
+        assert!(
+            labels.iter().map(String::as_str).eq(expected.iter().copied()),
+            "probes disposed in the wrong order:\n  expected: {expected:?}\n    actual: \
+             {labels:?}",
+        );
+    }
+
+    /// Assert that exactly one probe labeled `label` has been disposed.
+    ///
+    /// # Panics
+    /// This function panics if the label was logged zero or multiple times.
+    #[track_caller]
+    pub fn assert_disposed_once(&self, label: &str) {
+        let count = self.0.borrow().events.iter().filter(|e| e.label == label).count();
+
+        assert!(count == 1, "probe `{label}` was disposed {count} times, expected once");
+    }
+
+    /// Assert that every probe created by this recorder has been disposed
+    /// exactly once.
+    ///
+    /// # Panics
+    /// This function panics if any label was logged a different number of
+    /// times than a probe with that label was created.
+    #[track_caller]
+    pub fn assert_all_disposed_once(&self) {
+        let mut created = self.0.borrow().created.clone();
+        let mut disposed = self.labels();
+        created.sort();
+        disposed.sort();
+
+        assert!(
+            created == disposed,
+            "not every probe was disposed once:\n   created: {created:?}\n  disposed: \
+             {disposed:?}",
+        );
+    }
+
+    fn log(&self, label: &str, with: Option<&'static str>) {
+        let should_panic = {
+            let mut inner = self.0.borrow_mut();
+            inner.events.push(Event {
+                label: label.into(),
+                with,
+            });
+            inner.panic_on.contains(label)
+        };
+
+        assert!(!should_panic, "probe `{label}` panicked");
+    }
+}
+
+/// A value that logs its label to a [`Recorder`] when disposed.
+///
+/// [`Recorder`]: ./struct.Recorder.html
+#[derive(Debug)]
+pub struct Probe {
+    label: Cow<'static, str>,
+    rec: Recorder,
+}
+
+impl Probe {
+    /// The label this probe will log.
+    #[must_use]
+    pub fn label(&self) -> &str { &self.label }
+}
+
+impl Dispose for Probe {
+    fn dispose(self) { self.rec.log(&self.label, None); }
+}
+
+impl<W> DisposeWith<W> for Probe {
+    fn dispose_with(self, _: W) { self.rec.log(&self.label, Some(type_name::<W>())); }
+}
+
+impl AsyncDispose for Probe {
+    async fn dispose(self) { self.rec.log(&self.label, None); }
+}
+
+impl<W> AsyncDisposeWith<W> for Probe {
+    async fn dispose_with(self, _: W) { self.rec.log(&self.label, Some(type_name::<W>())); }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use std::{
+        panic::{catch_unwind, AssertUnwindSafe},
+        vec,
+    };
+
+    use super::*;
+    use crate::{block_on, defer, Disposable, DisposeIterator};
+
+    #[test]
+    fn records_order() {
+        let rec = Recorder::new();
+
+        {
+            let _a = Disposable::new(rec.probe("a"));
+            let _b = defer(rec.callback("b"));
+            DisposeWith::dispose_with(rec.probe("c"), 0_u8);
+        }
+
+        rec.assert_order(&["c", "b", "a"]);
+        rec.assert_all_disposed_once();
+        assert_eq!(rec.events()[0].with(), Some("u8"));
+    }
+
+    #[test]
+    fn injected_panic() {
+        let rec = Recorder::new();
+        let probes = rec.probes(["a", "b", "c"]);
+        rec.panic_on("b");
+
+        catch_unwind(AssertUnwindSafe(|| probes.dispose_iter())).unwrap_err();
+
+        rec.assert_order(&["a", "b", "c"]);
+    }
+
+    #[test]
+    fn async_probe() {
+        let rec = Recorder::new();
+
+        block_on(AsyncDispose::dispose(vec![rec.probe("a"), rec.probe("b")]));
+
+        rec.assert_order(&["a", "b"]);
+    }
+
+    #[test]
+    #[should_panic = "was disposed 0 times"]
+    fn missing_disposal() {
+        let rec = Recorder::new();
+        let _ = rec.probe("a");
+
+        rec.assert_disposed_once("a");
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    cell::Cell,
+    env,
+    process::{self, Command, ExitStatus, Output},
+    thread,
+};
+
+const TARGET_VAR: &str = "__DISPOSE_SUBPROCESS_TARGET";
+
+thread_local! {
+    static CALL_INDEX: Cell<usize> = const { Cell::new(0) };
+}
+
+/// How a child process started by [`run_in_subprocess`] exited.
+///
+/// [`run_in_subprocess`]: ./fn.run_in_subprocess.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildExit {
+    /// The closure returned normally.
+    Clean,
+    /// The closure panicked.
+    Panicked,
+    /// The process was aborted.
+    Aborted,
+    /// The process exited in some other way, with the given exit code if one
+    /// is available.
+    Other(Option<i32>),
+}
+
+impl ChildExit {
+    fn from_status(status: ExitStatus) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            if status.signal() == Some(6) {
+                return Self::Aborted;
+            }
+        }
+
+        match status.code() {
+            Some(0) => Self::Clean,
+            Some(101) => Self::Panicked,
+            // STATUS_STACK_BUFFER_OVERRUN, raised by __fastfail on Windows
+            #[cfg(windows)]
+            Some(c) if c as u32 == 0xC000_0409 => Self::Aborted,
+            c => Self::Other(c),
+        }
+    }
+}
+
+/// The outcome of running a closure in a child process.
+#[derive(Debug)]
+pub struct ChildOutcome {
+    exit: ChildExit,
+    output: Output,
+}
+
+impl ChildOutcome {
+    /// How the child process exited.
+    #[must_use]
+    pub fn exit(&self) -> ChildExit { self.exit }
+
+    /// The captured standard error of the child process.
+    #[must_use]
+    pub fn stderr(&self) -> String { String::from_utf8_lossy(&self.output.stderr).into_owned() }
+}
+
+/// Run `f` in a child process, reporting how that process exited.
+///
+/// This allows testing code that is expected to abort the process, such as an
+/// [`AbortCanary`].  It must be called from within a test run by the standard
+/// test harness; the child process re-executes the current test binary,
+/// filtered to the current test (identified by the name of the current
+/// thread).  Inside the child, the test runs up to the matching call to
+/// `run_in_subprocess`, runs `f`, and exits.
+///
+/// Because the child re-runs the test from the beginning, any code before the
+/// call to `run_in_subprocess` runs in both processes.  Within the child, any
+/// earlier calls to `run_in_subprocess` in the same test do nothing and return
+/// `None`.  In the parent process, this function always returns `Some`.
+///
+/// # Panics
+/// This function panics if the current thread has no name, or if the child
+/// process could not be started.
+///
+/// [`AbortCanary`]: ../struct.AbortCanary.html
+pub fn run_in_subprocess(f: impl FnOnce()) -> Option<ChildOutcome> {
+    let index = CALL_INDEX.with(|i| i.replace(i.get() + 1));
+
+    if let Ok(target) = env::var(TARGET_VAR) {
+        if target.parse::<usize>().ok() == Some(index) {
+            f();
+            process::exit(0);
+        }
+
+        return None;
+    }
+
+    let thread = thread::current();
+    let name = thread
+        .name()
+        .filter(|n| *n != "main")
+        .expect("run_in_subprocess must be called from a test thread");
+
+    let output = Command::new(env::current_exe().expect("Couldn't locate the test binary"))
+        .args([name, "--exact", "--nocapture", "--test-threads=1"])
+        .env(TARGET_VAR, index.to_string())
+        .output()
+        .expect("Couldn't start the child process");
+
+    Some(ChildOutcome {
+        exit: ChildExit::from_status(output.status),
+        output,
+    })
+}
+
+/// Assert that running `f` aborts the process.
+///
+/// `f` is run in a child process using [`run_in_subprocess`], and the same
+/// caveats apply.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dispose::{testing::assert_aborts, AbortCanary};
+///
+/// #[test]
+/// fn dropped_canary_aborts() {
+///     assert_aborts(|| drop(AbortCanary::new()));
+/// }
+/// ```
+///
+/// # Panics
+/// This function panics if the child process does not abort.
+///
+/// [`run_in_subprocess`]: ./fn.run_in_subprocess.html
+#[track_caller]
+pub fn assert_aborts(f: impl FnOnce()) {
+    if let Some(out) = run_in_subprocess(f) {
+        assert!(
+            out.exit() == ChildExit::Aborted,
+            "expected the child process to abort, but got {:?}; stderr:\n{}",
+            out.exit(),
+            out.stderr(),
+        );
+    }
+}
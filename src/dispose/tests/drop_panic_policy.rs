@@ -0,0 +1,28 @@
+//! Changing the drop panic policy affects every test running in the same
+//! process, so the tests that do so live in their own binary.
+
+#![cfg(feature = "std")]
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use dispose::{set_drop_panic_policy, Disposable, Dispose, DropPanicPolicy};
+
+struct Panicky;
+
+impl Dispose for Panicky {
+    fn dispose(self) { panic!("inner"); }
+}
+
+#[test]
+fn policy_catches_during_unwind() {
+    let prev = set_drop_panic_policy(DropPanicPolicy::Log);
+
+    let err = catch_unwind(AssertUnwindSafe(|| {
+        let _d = Disposable::new(Panicky);
+        panic!("outer");
+    }))
+    .unwrap_err();
+
+    set_drop_panic_policy(prev);
+    assert_eq!(err.downcast_ref(), Some(&"outer"));
+}
@@ -0,0 +1,434 @@
+#[cfg(feature = "alloc")]
+use alloc::string::ToString;
+use core::{any::type_name, convert::Infallible, fmt};
+
+#[cfg(feature = "alloc")]
+use crate::{unwind::Panics, DisposeErrors};
+use crate::{hook::Hook, Dispose, DisposeReason, DisposeWith};
+
+/// A fallible counterpart to [`Dispose`], for values whose teardown can fail.
+///
+/// Some resources cannot always be released cleanly &mdash; flushing a file
+/// can fail, a GPU device can be lost, and so on.  Rather than swallowing
+/// these errors or turning them into panics, such types can implement this
+/// trait to report them to the caller.
+///
+/// All types implementing [`Dispose`] automatically implement `TryDispose`
+/// with an error type of [`Infallible`], so any value that can be disposed can
+/// also be used where a `TryDispose` value is expected.
+///
+/// Errors must be `Send`, `Sync`, and `'static`, so that containers disposing
+/// several values (such as those using the [`TryDispose`][derive] derive macro
+/// or [`TryDisposeIterator`]) can collect them into a [`DisposeErrors`].  Types
+/// whose errors borrow data or are not thread-safe should convert them into an
+/// owned error, such as a message string, before returning them.
+///
+/// When a value implementing `TryDispose` is dropped inside a [`Disposable`],
+/// any error it produces is passed to the hook registered with
+/// [`set_drop_error_hook`].  To handle the error directly, use
+/// [`Disposable::try_dispose`].
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{Disposable, TryDispose};
+///
+/// struct Connection {
+///     healthy: bool,
+/// }
+///
+/// impl TryDispose for Connection {
+///     type Error = &'static str;
+///
+///     fn try_dispose(self) -> Result<(), &'static str> {
+///         if self.healthy { Ok(()) } else { Err("connection reset by peer") }
+///     }
+/// }
+///
+/// let conn = Disposable::new(Connection { healthy: false });
+///
+/// assert_eq!(Disposable::try_dispose(conn), Err("connection reset by peer"));
+/// ```
+///
+/// [`Dispose`]: ./trait.Dispose.html
+/// [derive]: ./derive.TryDispose.html
+/// [`TryDisposeIterator`]: ./trait.TryDisposeIterator.html
+/// [`DisposeErrors`]: ./struct.DisposeErrors.html
+/// [`Disposable`]: ./struct.Disposable.html
+/// [`Disposable::try_dispose`]: ./struct.Disposable.html#method.try_dispose
+/// [`set_drop_error_hook`]: ./fn.set_drop_error_hook.html
+pub trait TryDispose {
+    /// The error produced if disposal fails.
+    type Error: fmt::Debug + Send + Sync + 'static;
+
+    /// Consume self and deinitialize its contents, reporting any errors that
+    /// occur.
+    ///
+    /// # Errors
+    /// This function should return an error if the value could not be cleanly
+    /// deinitialized.  Regardless of the outcome, `self` is consumed.
+    fn try_dispose(self) -> Result<(), Self::Error>;
+
+    /// Consume self and deinitialize its contents, given the reason it is
+    /// being disposed.  See [`Dispose::dispose_because`] for more info.
+    ///
+    /// The default implementation ignores the reason and calls
+    /// [`try_dispose`].
+    ///
+    /// # Errors
+    /// This function should return an error if the value could not be cleanly
+    /// deinitialized.
+    ///
+    /// [`Dispose::dispose_because`]: ./trait.Dispose.html#method.dispose_because
+    /// [`try_dispose`]: ./trait.TryDispose.html#tymethod.try_dispose
+    fn try_dispose_because(self, reason: DisposeReason) -> Result<(), Self::Error>
+    where Self: Sized {
+        let _ = reason;
+        self.try_dispose()
+    }
+}
+
+impl<T: Dispose> TryDispose for T {
+    type Error = Infallible;
+
+    /// Dispose `self`, which cannot fail.
+    fn try_dispose(self) -> Result<(), Infallible> {
+        self.dispose();
+        Ok(())
+    }
+
+    fn try_dispose_because(self, reason: DisposeReason) -> Result<(), Infallible> {
+        self.dispose_because(reason);
+        Ok(())
+    }
+}
+
+/// A fallible counterpart to [`DisposeWith`].
+///
+/// As with [`TryDispose`], all types implementing [`DisposeWith<W>`] also
+/// implement `TryDisposeWith<W>` with an error type of [`Infallible`], and
+/// errors must be `Send`, `Sync`, and `'static`.
+///
+/// [`DisposeWith`]: ./trait.DisposeWith.html
+/// [`DisposeWith<W>`]: ./trait.DisposeWith.html
+/// [`TryDispose`]: ./trait.TryDispose.html
+pub trait TryDisposeWith<W> {
+    /// The error produced if disposal fails.
+    type Error: fmt::Debug + Send + Sync + 'static;
+
+    /// Dispose self, using the provided value, and reporting any errors that
+    /// occur.
+    ///
+    /// # Errors
+    /// This function should return an error if the value could not be cleanly
+    /// deinitialized.  Regardless of the outcome, both `self` and `with` are
+    /// consumed.
+    fn try_dispose_with(self, with: W) -> Result<(), Self::Error>;
+
+    /// Dispose self, using the provided value, given the reason it is being
+    /// disposed.  See [`Dispose::dispose_because`] for more info.
+    ///
+    /// The default implementation ignores the reason and calls
+    /// [`try_dispose_with`].
+    ///
+    /// # Errors
+    /// This function should return an error if the value could not be cleanly
+    /// deinitialized.
+    ///
+    /// [`Dispose::dispose_because`]: ./trait.Dispose.html#method.dispose_because
+    /// [`try_dispose_with`]: ./trait.TryDisposeWith.html#tymethod.try_dispose_with
+    fn try_dispose_with_because(self, with: W, reason: DisposeReason) -> Result<(), Self::Error>
+    where Self: Sized {
+        let _ = reason;
+        self.try_dispose_with(with)
+    }
+}
+
+impl<W, T: DisposeWith<W>> TryDisposeWith<W> for T {
+    type Error = Infallible;
+
+    /// Dispose `self` with the given value, which cannot fail.
+    fn try_dispose_with(self, with: W) -> Result<(), Infallible> {
+        self.dispose_with(with);
+        Ok(())
+    }
+
+    fn try_dispose_with_because(self, with: W, reason: DisposeReason) -> Result<(), Infallible> {
+        self.dispose_with_because(with, reason);
+        Ok(())
+    }
+}
+
+/// A helper trait for iterators with items implementing [`TryDispose`].
+///
+/// This is the fallible equivalent of [`DisposeIterator`].  All items are
+/// disposed even if some of them fail or panic, and any errors are collected
+/// into a [`DisposeErrors`] labeled by the index of the item that produced
+/// them.
+///
+/// [`TryDispose`]: ./trait.TryDispose.html
+/// [`DisposeIterator`]: ./trait.DisposeIterator.html
+/// [`DisposeErrors`]: ./struct.DisposeErrors.html
+#[cfg(feature = "alloc")]
+pub trait TryDisposeIterator {
+    /// Dispose all items in the iterator, consuming it.
+    ///
+    /// # Errors
+    /// This function returns an error if any item failed to dispose.
+    fn try_dispose_iter(self) -> Result<(), DisposeErrors>;
+
+    /// Dispose all items in the iterator, consuming it and passing `reason` to
+    /// each item.  See [`Dispose::dispose_because`] for more info.
+    ///
+    /// The default implementation ignores the reason and calls
+    /// [`try_dispose_iter`].
+    ///
+    /// # Errors
+    /// This function returns an error if any item failed to dispose.
+    ///
+    /// [`Dispose::dispose_because`]: ./trait.Dispose.html#method.dispose_because
+    /// [`try_dispose_iter`]: ./trait.TryDisposeIterator.html#tymethod.try_dispose_iter
+    fn try_dispose_iter_because(self, reason: DisposeReason) -> Result<(), DisposeErrors>
+    where Self: Sized {
+        let _ = reason;
+        self.try_dispose_iter()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I: IntoIterator> TryDisposeIterator for I
+where I::Item: TryDispose
+{
+    fn try_dispose_iter(self) -> Result<(), DisposeErrors> {
+        self.try_dispose_iter_because(DisposeReason::Explicit)
+    }
+
+    fn try_dispose_iter_because(self, reason: DisposeReason) -> Result<(), DisposeErrors> {
+        let mut errors = DisposeErrors::new();
+        let mut panics = Panics::new();
+
+        for (i, el) in self.into_iter().enumerate() {
+            panics.run(|| errors.record(i.to_string(), el.try_dispose_because(reason)));
+        }
+
+        panics.resume();
+        errors.into_result()
+    }
+}
+
+/// A helper trait for iterators with items implementing [`TryDisposeWith`].
+///
+/// This is the fallible equivalent of [`DisposeIteratorWith`], and behaves
+/// similarly to [`TryDisposeIterator`].
+///
+/// [`TryDisposeWith`]: ./trait.TryDisposeWith.html
+/// [`DisposeIteratorWith`]: ./trait.DisposeIteratorWith.html
+/// [`TryDisposeIterator`]: ./trait.TryDisposeIterator.html
+#[cfg(feature = "alloc")]
+pub trait TryDisposeIteratorWith<W> {
+    /// Dispose all items in the iterator using the provided value, consuming
+    /// both.
+    ///
+    /// # Errors
+    /// This function returns an error if any item failed to dispose.
+    fn try_dispose_iter_with(self, with: W) -> Result<(), DisposeErrors>;
+
+    /// Dispose all items in the iterator using the provided value, passing
+    /// `reason` to each item.  See [`Dispose::dispose_because`] for more info.
+    ///
+    /// The default implementation ignores the reason and calls
+    /// [`try_dispose_iter_with`].
+    ///
+    /// # Errors
+    /// This function returns an error if any item failed to dispose.
+    ///
+    /// [`Dispose::dispose_because`]: ./trait.Dispose.html#method.dispose_because
+    /// [`try_dispose_iter_with`]:
+    ///     ./trait.TryDisposeIteratorWith.html#tymethod.try_dispose_iter_with
+    fn try_dispose_iter_with_because(
+        self,
+        with: W,
+        reason: DisposeReason,
+    ) -> Result<(), DisposeErrors>
+    where Self: Sized {
+        let _ = reason;
+        self.try_dispose_iter_with(with)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<W: Copy, I: IntoIterator> TryDisposeIteratorWith<W> for I
+where I::Item: TryDisposeWith<W>
+{
+    /// Dispose all items in the iterator, using copies of the provided value.
+    fn try_dispose_iter_with(self, with: W) -> Result<(), DisposeErrors> {
+        self.try_dispose_iter_with_because(with, DisposeReason::Explicit)
+    }
+
+    fn try_dispose_iter_with_because(
+        self,
+        with: W,
+        reason: DisposeReason,
+    ) -> Result<(), DisposeErrors> {
+        let mut errors = DisposeErrors::new();
+        let mut panics = Panics::new();
+
+        for (i, el) in self.into_iter().enumerate() {
+            panics.run(|| errors.record(i.to_string(), el.try_dispose_with_because(with, reason)));
+        }
+
+        panics.resume();
+        errors.into_result()
+    }
+}
+
+/// Information about an error that occurred while a [`Disposable`] was being
+/// dropped.
+///
+/// This is passed to the hook registered with [`set_drop_error_hook`].
+///
+/// [`Disposable`]: ./struct.Disposable.html
+/// [`set_drop_error_hook`]: ./fn.set_drop_error_hook.html
+#[derive(Debug, Clone, Copy)]
+pub struct DropErrorInfo<'a> {
+    type_name: &'static str,
+    error: &'a dyn fmt::Debug,
+}
+
+impl<'a> DropErrorInfo<'a> {
+    /// The name of the type that failed to dispose.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str { self.type_name }
+
+    /// The error produced by [`try_dispose`].
+    ///
+    /// [`try_dispose`]: ./trait.TryDispose.html#tymethod.try_dispose
+    #[must_use]
+    pub fn error(&self) -> &'a dyn fmt::Debug { self.error }
+}
+
+impl fmt::Display for DropErrorInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "error while disposing value of type `{}`: {:?}",
+            self.type_name, self.error
+        )
+    }
+}
+
+static DROP_ERROR_HOOK: Hook<fn(&DropErrorInfo)> = unsafe { Hook::new() };
+
+/// Register a hook to be called when a [`Disposable`] fails to dispose its
+/// contents while being dropped, replacing any previously-registered hook.
+///
+/// Since `Drop::drop` cannot return a value, errors produced by
+/// [`TryDispose::try_dispose`] during a drop are passed to this hook instead.
+/// If no hook is registered, [`default_drop_error_hook`] is used.
+///
+/// # Examples
+///
+/// ```
+/// use dispose::{set_drop_error_hook, take_drop_error_hook};
+///
+/// set_drop_error_hook(|info| panic!("{info}"));
+///
+/// // ...
+///
+/// // Restore the default behavior
+/// take_drop_error_hook();
+/// ```
+///
+/// [`Disposable`]: ./struct.Disposable.html
+/// [`TryDispose::try_dispose`]: ./trait.TryDispose.html#tymethod.try_dispose
+/// [`default_drop_error_hook`]: ./fn.default_drop_error_hook.html
+pub fn set_drop_error_hook(hook: fn(&DropErrorInfo)) { DROP_ERROR_HOOK.set(hook); }
+
+/// Unregister the current drop error hook, returning it.
+///
+/// If no hook was registered, [`default_drop_error_hook`] is returned.
+///
+/// [`default_drop_error_hook`]: ./fn.default_drop_error_hook.html
+pub fn take_drop_error_hook() -> fn(&DropErrorInfo) {
+    DROP_ERROR_HOOK.take().unwrap_or(default_drop_error_hook)
+}
+
+/// The default drop error hook, which prints the error to standard error.
+///
+/// Without the `std` feature, this function does nothing.
+pub fn default_drop_error_hook(info: &DropErrorInfo) {
+    #[cfg(feature = "std")]
+    eprintln!("{info}");
+    #[cfg(not(feature = "std"))]
+    let _ = info;
+}
+
+/// Pass an error produced while dropping a value of type `T` to the current
+/// drop error hook.
+pub(crate) fn report_drop_error<T>(error: &dyn fmt::Debug) {
+    let info = DropErrorInfo {
+        type_name: type_name::<T>(),
+        error,
+    };
+
+    DROP_ERROR_HOOK.get().unwrap_or(default_drop_error_hook)(&info);
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::Disposable;
+
+    struct Fallible(Rc<Cell<bool>>, Result<(), u32>);
+
+    impl TryDispose for Fallible {
+        type Error = u32;
+
+        fn try_dispose(self) -> Result<(), u32> {
+            self.0.set(true);
+            self.1
+        }
+    }
+
+    #[test]
+    fn explicit_try_dispose() {
+        let flag = Rc::new(Cell::new(false));
+
+        let ok = Disposable::new(Fallible(Rc::clone(&flag), Ok(())));
+        assert_eq!(Disposable::try_dispose(ok), Ok(()));
+        assert!(flag.replace(false));
+
+        let err = Disposable::new(Fallible(Rc::clone(&flag), Err(42)));
+        assert_eq!(Disposable::try_dispose(err), Err(42));
+        assert!(flag.get());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn iter_collects_errors() {
+        let flag = Rc::new(Cell::new(false));
+
+        let errs = vec![
+            Fallible(Rc::clone(&flag), Err(1)),
+            Fallible(Rc::clone(&flag), Ok(())),
+            Fallible(Rc::clone(&flag), Err(3)),
+        ]
+        .try_dispose_iter()
+        .unwrap_err();
+
+        let errs: Vec<_> = errs.iter().map(|(n, e)| format!("{n}: {e:?}")).collect();
+        assert_eq!(errs, ["0: 1", "2: 3"]);
+    }
+
+    #[test]
+    fn infallible_bridge() {
+        let flag = Rc::new(Cell::new(false));
+        let flag2 = Rc::clone(&flag);
+
+        let res = (move || flag2.set(true)).try_dispose();
+        assert!(res.is_ok());
+        assert!(flag.get());
+    }
+}
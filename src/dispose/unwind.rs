@@ -0,0 +1,147 @@
+#[cfg(not(feature = "std"))]
+use core::convert::Infallible;
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+#[cfg(feature = "std")]
+use std::{
+    any::Any,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+};
+
+/// The payload of a caught panic.
+#[cfg(feature = "std")]
+pub type PanicPayload = Box<dyn Any + Send + 'static>;
+
+/// The payload of a caught panic.  Without the `std` feature, panics cannot be
+/// caught, so this type is uninhabited.
+#[cfg(not(feature = "std"))]
+pub type PanicPayload = Infallible;
+
+/// Collects panics raised while disposing a sequence of values, so that the
+/// remaining values can still be disposed.
+///
+/// Without the `std` feature, panics are not caught and this type does
+/// nothing.
+///
+/// Once every value has been visited, [`resume`](Self::resume) re-raises the
+/// first panic caught, if any.  Any subsequent panics are discarded, mirroring
+/// the behavior of dropping a `Vec` whose elements panic.
+#[derive(Debug, Default)]
+#[allow(missing_copy_implementations)] // Only Copy without std
+pub struct Panics(Option<PanicPayload>);
+
+impl Panics {
+    /// Construct a new, empty collector.
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
+
+    /// Run `f`, catching any panic it raises.
+    pub fn run<R>(&mut self, f: impl FnOnce() -> R) -> Option<R> {
+        #[cfg(feature = "std")]
+        return self.record(catch_unwind(AssertUnwindSafe(f)));
+        #[cfg(not(feature = "std"))]
+        return Some(f());
+    }
+
+    /// Record the outcome of an operation that may have panicked.
+    pub fn record<R>(&mut self, res: Result<R, PanicPayload>) -> Option<R> {
+        match res {
+            Ok(r) => Some(r),
+            Err(p) => {
+                self.0.get_or_insert(p);
+                None
+            },
+        }
+    }
+
+    /// Re-raise the first panic caught, if any.
+    pub fn resume(self) {
+        if let Some(p) = self.0 {
+            #[cfg(feature = "std")]
+            resume_unwind(p);
+            #[cfg(not(feature = "std"))]
+            match p {}
+        }
+    }
+}
+
+/// A future that catches any panics raised while polling the inner future.
+#[derive(Debug)]
+pub struct CatchUnwind<F>(F);
+
+impl<F: Future> CatchUnwind<F> {
+    /// Wrap the given future.
+    pub fn new(fut: F) -> Self { Self(fut) }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, PanicPayload>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        // SAFETY: the inner future is never moved out of self
+        let fut = unsafe { self.map_unchecked_mut(|s| &mut s.0) };
+
+        #[cfg(feature = "std")]
+        return match catch_unwind(AssertUnwindSafe(|| fut.poll(cx))) {
+            Ok(Poll::Ready(r)) => Poll::Ready(Ok(r)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(p) => Poll::Ready(Err(p)),
+        };
+        #[cfg(not(feature = "std"))]
+        return fut.poll(cx).map(Ok);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use std::{
+        cell::RefCell,
+        panic::{catch_unwind, AssertUnwindSafe},
+        rc::Rc,
+    };
+
+    use crate::{Dispose, DisposeIterator, DisposeIteratorWith, DisposeWith};
+
+    struct Res(u32, Rc<RefCell<Vec<u32>>>);
+
+    impl Dispose for Res {
+        fn dispose(self) {
+            assert!(self.0 != 2, "oh no");
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    impl DisposeWith<u32> for Res {
+        fn dispose_with(self, with: u32) {
+            assert!(self.0 != with, "oh no");
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    fn resources(log: &Rc<RefCell<Vec<u32>>>) -> Vec<Res> {
+        (1..=4).map(|i| Res(i, Rc::clone(log))).collect()
+    }
+
+    #[test]
+    fn iter_continues_after_panic() {
+        let log = Rc::default();
+        let res = resources(&log);
+
+        let err = catch_unwind(AssertUnwindSafe(|| res.dispose_iter())).unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&"oh no"));
+        assert_eq!(*log.borrow(), [1, 3, 4]);
+    }
+
+    #[test]
+    fn iter_with_continues_after_panic() {
+        let log = Rc::default();
+        let res = resources(&log);
+
+        let err = catch_unwind(AssertUnwindSafe(|| res.dispose_iter_with(3))).unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&"oh no"));
+        assert_eq!(*log.borrow(), [1, 2, 4]);
+    }
+}